@@ -1,88 +1,43 @@
-use crate::animation::{AnimationStages, AnimationTimer, FlipSpriteSheetBasedOnVelocity, Frame};
+use crate::animation::graph::{BehaviorLabel, SpriteAnimator, SpriteAnimatorSystems};
+use crate::animation::AnimationStages;
 use crate::animation::pop::PopAnimationBundle;
-use crate::behavior::eggman_control::{DeadEggman, EggmanLifecycleCharacteristics, EggmanState};
-use crate::engine::physics::Velocity;
-use crate::entities::eggman::Eggman;
-use crate::entities::spritesheets::{EggTextureAtlas, PopTextureAtlas};
+use crate::behavior::eggman_control::{DeadEggman, EggmanState};
+use crate::entities::spritesheets::SpriteSheetRegistry;
 use bevy::prelude::*;
 
+/// Frames and transitions are in `assets/animations/eggman.anim.ron`
 pub struct EggmanAnimationPlugin;
 impl Plugin for EggmanAnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (compute_next_frame, animate_dead_eggmen).in_set(AnimationStages::CalculateNextStates));
+        app.add_systems(
+            Update,
+            (
+                label_behavior.before(SpriteAnimatorSystems),
+                animate_dead_eggmen.run_if(resource_exists::<SpriteSheetRegistry>()),
+            )
+                .in_set(AnimationStages::CalculateNextStates),
+        );
     }
 }
 
 #[derive(Bundle)]
 pub struct EggmanAnimationBundle {
-    pub timer: AnimationTimer,
-    pub frame: Frame,
-    flip: FlipSpriteSheetBasedOnVelocity,
+    pub animator: SpriteAnimator,
+    label: BehaviorLabel,
 }
 impl Default for EggmanAnimationBundle {
     fn default() -> Self {
-        let animation_duration = 0.8;
         Self {
-            timer: AnimationTimer(Timer::from_seconds(animation_duration, TimerMode::Once)),
-            frame: Frame(0),
-            flip: FlipSpriteSheetBasedOnVelocity,
+            animator: SpriteAnimator::new("eggman", "egg"),
+            label: BehaviorLabel(EggmanState::JustSpawned.label()),
         }
     }
 }
 
-fn compute_next_frame(
-    mut q: Query<(
-        &EggmanState,
-        &EggmanLifecycleCharacteristics,
-        &mut Frame,
-        &mut AnimationTimer,
-        &Velocity,
-    )>,
-    time: Res<Time>,
-) {
-    for (es, elc, mut frame, mut at, vel) in q.iter_mut() {
-        use EggmanState::*;
-
-        match es {
-            JustSpawned => {}
-            Egg => {
-                // No animation occurs
-            }
-            Hatching => {
-                // Flips between the first and the second frame
-                at.tick(time.delta());
-
-                if at.finished() {
-                    **frame = (frame.0 + 1) % 2;
-                    at.reset()
-                }
-            }
-            Hatched => {
-                if frame.0 > 4 {
-                    break;
-                }
-                // Flips from the third to the fifth frame
-                at.tick(time.delta());
-
-                if at.finished() {
-                    **frame = frame.0 + 1;
-                    at.reset()
-                }
-            }
-            Seeking => {
-                at.tick(time.delta());
-
-                if at.finished() {
-                    let animation_speed_thresh = 0.2;
-                    if vel.x > animation_speed_thresh {
-                        **frame = frame.0 % 2 + 5;
-                        at.reset()
-                    } else {
-                        **frame = 5;
-                    }
-                }
-            }
-        }
+/// Lets the animation graph follow the lifecycle
+fn label_behavior(mut q: Query<(&EggmanState, &mut BehaviorLabel), Changed<EggmanState>>) {
+    for (es, mut label) in q.iter_mut() {
+        label.0 = es.label();
     }
 }
 
@@ -90,14 +45,17 @@ fn compute_next_frame(
 fn animate_dead_eggmen(
     mut c: Commands,
     q: Query<Entity, Added<DeadEggman>>,
-    q_pop_tex: Query<&Handle<TextureAtlas>, With<PopTextureAtlas>>,
+    registry: Res<SpriteSheetRegistry>,
 ) {
     for dead_ent in q.iter() {
-        let pop_tex = q_pop_tex.single().clone();
+        let Some(pop_tex) = registry.atlas("pop") else {
+            warn!(target: "joust::assets", "No sprite sheet loaded for pops");
+            continue;
+        };
         let pop_animation_bundle = PopAnimationBundle::default();
         let mut ent = c.entity(dead_ent);
-        ent.remove::<EggTextureAtlas>();
-        ent.insert(pop_tex);
+        ent.remove::<SpriteAnimator>();
+        ent.insert(pop_tex.clone());
         ent.insert(pop_animation_bundle);
     }
 }
@@ -0,0 +1,425 @@
+/*
+Data driven sprite animation
+
+An animation graph is loaded from `assets/animations/<name>.anim.ron`. Each
+state of the graph plays a clip of the sprite sheet registry, and lists
+transitions to other states. Every frame, the transitions of the current
+state are checked in order, after the transitions listed under `any`. The
+first transition whose conditions all hold is taken. A transition to the
+current state keeps the clip playing, unless it asks for a restart.
+
+Insert a SpriteAnimator to animate an entity with a graph. Conditions read
+the Velocity, Acceleration, Grounded, MovementControl and BehaviorLabel
+components of the entity, when it has them.
+*/
+
+use crate::animation::AnimationStages;
+use crate::behavior::movement_control::MovementControl;
+use crate::engine::collision::{Grounded, GroundedState};
+use crate::engine::physics::{Acceleration, Velocity, V2};
+use crate::engine::simulation_clock::SimulationClock;
+use crate::entities::spritesheets::{SheetClip, SpriteSheetRegistry};
+use crate::loading::RequiredAssets;
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+
+/// Graphs loaded at startup, by name
+const GRAPHS: [&str; 3] = ["rider", "pter", "eggman"];
+
+pub struct SpriteAnimationGraphPlugin;
+impl Plugin for SpriteAnimationGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpriteAnimationGraph>();
+        app.init_asset_loader::<SpriteAnimationGraphLoader>();
+        app.add_systems(Startup, load_graphs);
+        app.add_systems(
+            Update,
+            (
+                run_animators
+                    .in_set(AnimationStages::CalculateNextStates)
+                    .in_set(SpriteAnimatorSystems)
+                    .run_if(resource_exists::<SpriteSheetRegistry>()),
+                apply_animators.in_set(AnimationStages::ApplyAnimationState),
+            ),
+        );
+    }
+}
+
+/// Animators pick their next state and frame in this set, during CalculateNextStates
+#[derive(SystemSet, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub struct SpriteAnimatorSystems;
+
+/// Which way the sprite faces
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Flip {
+    /// The sprite is never flipped
+    None,
+    /// Faces the direction of the velocity
+    Velocity,
+    /// Faces the direction of the movement control
+    Control,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum AnimationCondition {
+    Grounded,
+    Airborne,
+    VelocityXAbove(f32),
+    VelocityXBelow(f32),
+    VelocityYAbove(f32),
+    VelocityYBelow(f32),
+    /// The absolute horizontal velocity is above this
+    SpeedXAbove(f32),
+    SpeedXBelow(f32),
+    /// The absolute vertical velocity is above this
+    SpeedYAbove(f32),
+    SpeedYBelow(f32),
+    AccelerationYAbove(f32),
+    /// Accelerating against the horizontal velocity, acceleration times velocity is below the negative of this
+    Braking(f32),
+    /// Accelerating with the horizontal velocity, acceleration times velocity is above this
+    Accelerating(f32),
+    /// The absolute horizontal movement control is above this
+    SteeringAbove(f32),
+    SteeringBelow(f32),
+    /// The last frame of a clip that does not loop has been shown for its duration
+    ClipFinished,
+    TimeInStateAbove(f32),
+    /// Holds once every this many seconds
+    Periodic(f32),
+    /// The BehaviorLabel of the entity is this
+    Behavior(String),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Transition {
+    pub to: String,
+    #[serde(default)]
+    pub when: Vec<AnimationCondition>,
+    /// Restarts the clip when the transition goes to the current state
+    #[serde(default)]
+    pub restart: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateDef {
+    /// Name of the clip in the sprite sheet of the animated entity
+    pub clip: String,
+    /// Replaces the flip of the graph
+    #[serde(default)]
+    pub flip: Option<Flip>,
+    /// After the first frame, frames last this divided by the horizontal speed
+    #[serde(default)]
+    pub duration_per_speed: Option<f32>,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+#[derive(Asset, TypePath, Clone, Debug, Deserialize)]
+pub struct SpriteAnimationGraph {
+    pub initial: String,
+    #[serde(default = "default_flip")]
+    pub flip: Flip,
+    /// Checked before the transitions of the current state
+    #[serde(default)]
+    pub any: Vec<Transition>,
+    pub states: HashMap<String, StateDef>,
+}
+
+fn default_flip() -> Flip {
+    Flip::None
+}
+
+#[derive(Default)]
+pub struct SpriteAnimationGraphLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpriteAnimationGraphLoaderError {
+    #[error("Could not read the animation graph: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the animation graph: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("The animation graph has no state named {0}")]
+    UnknownState(String),
+}
+
+impl AssetLoader for SpriteAnimationGraphLoader {
+    type Asset = SpriteAnimationGraph;
+    type Settings = ();
+    type Error = SpriteAnimationGraphLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SpriteAnimationGraph, SpriteAnimationGraphLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let graph = ron::de::from_bytes::<SpriteAnimationGraph>(&bytes)?;
+
+            // Every transition has to lead somewhere
+            let targets = std::iter::once(&graph.initial).chain(
+                graph
+                    .any
+                    .iter()
+                    .chain(graph.states.values().flat_map(|s| s.transitions.iter()))
+                    .map(|t| &t.to),
+            );
+            for target in targets {
+                if !graph.states.contains_key(target) {
+                    return Err(SpriteAnimationGraphLoaderError::UnknownState(target.clone()));
+                }
+            }
+            Ok(graph)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct SpriteAnimationGraphs(HashMap<&'static str, Handle<SpriteAnimationGraph>>);
+
+fn load_graphs(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut required: ResMut<RequiredAssets>,
+) {
+    let graphs = GRAPHS
+        .iter()
+        .map(|name| {
+            let handle: Handle<SpriteAnimationGraph> =
+                asset_server.load(format!("animations/{}.anim.ron", name));
+            required.add(handle.clone());
+            (*name, handle)
+        })
+        .collect();
+    commands.insert_resource(SpriteAnimationGraphs(graphs));
+}
+
+/// Label of the behavior state of an entity, for Behavior conditions
+#[derive(Component, Clone, Copy, Deref)]
+pub struct BehaviorLabel(pub &'static str);
+
+/// Animates the sprite of this entity with an animation graph
+#[derive(Component)]
+pub struct SpriteAnimator {
+    /// Name of the graph
+    graph: &'static str,
+    /// Name of the sprite sheet the clips are looked up in
+    sheet: &'static str,
+    /// Entered once the graph is loaded
+    state: Option<String>,
+    frame: usize,
+    clip_first: usize,
+    frame_elapsed: f32,
+    frame_duration: f32,
+    clip_finished: bool,
+    time_in_state: f32,
+    elapsed: f32,
+    flip_x: bool,
+}
+impl SpriteAnimator {
+    pub fn new(graph: &'static str, sheet: &'static str) -> Self {
+        Self {
+            graph,
+            sheet,
+            state: None,
+            frame: 0,
+            clip_first: 0,
+            frame_elapsed: 0.0,
+            frame_duration: 0.0,
+            clip_finished: false,
+            time_in_state: 0.0,
+            elapsed: 0.0,
+            flip_x: false,
+        }
+    }
+
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    /// How many frames into the clip of the current state the animation is
+    pub fn frame_in_clip(&self) -> usize {
+        self.frame - self.clip_first
+    }
+
+    fn enter(&mut self, state: &str, clip: &SheetClip) {
+        self.state = Some(state.to_string());
+        self.frame = clip.first;
+        self.clip_first = clip.first;
+        self.frame_elapsed = 0.0;
+        self.frame_duration = clip.frame_duration;
+        self.clip_finished = false;
+        self.time_in_state = 0.0;
+    }
+
+    /// Shows the next frame of the clip once the current one has been shown long enough
+    fn advance(&mut self, dt: f32, clip: &SheetClip, speed_x: f32, duration_per_speed: Option<f32>) {
+        self.frame_elapsed += dt;
+        if self.frame_elapsed < self.frame_duration {
+            return;
+        }
+        self.frame_elapsed -= self.frame_duration;
+        if self.frame < clip.last {
+            self.frame += 1;
+        } else if clip.looping {
+            self.frame = clip.first;
+        } else {
+            self.clip_finished = true;
+            self.frame_elapsed = 0.0;
+        }
+        self.frame_duration = match duration_per_speed {
+            Some(d) if speed_x > 0.0 => d / speed_x,
+            _ => clip.frame_duration,
+        };
+    }
+}
+
+/// What the conditions of a transition are checked against
+struct ConditionContext<'a> {
+    velocity: V2,
+    acceleration: V2,
+    grounded: bool,
+    steering: f32,
+    label: Option<&'a str>,
+    animator: &'a SpriteAnimator,
+    dt: f32,
+}
+
+impl AnimationCondition {
+    fn holds(&self, ctx: &ConditionContext) -> bool {
+        let (v, a) = (ctx.velocity, ctx.acceleration);
+        match self {
+            Self::Grounded => ctx.grounded,
+            Self::Airborne => !ctx.grounded,
+            Self::VelocityXAbove(t) => v.x > *t,
+            Self::VelocityXBelow(t) => v.x < *t,
+            Self::VelocityYAbove(t) => v.y > *t,
+            Self::VelocityYBelow(t) => v.y < *t,
+            Self::SpeedXAbove(t) => v.x.abs() > *t,
+            Self::SpeedXBelow(t) => v.x.abs() < *t,
+            Self::SpeedYAbove(t) => v.y.abs() > *t,
+            Self::SpeedYBelow(t) => v.y.abs() < *t,
+            Self::AccelerationYAbove(t) => a.y > *t,
+            Self::Braking(t) => a.x * v.x < -*t,
+            Self::Accelerating(t) => a.x * v.x > *t,
+            Self::SteeringAbove(t) => ctx.steering.abs() > *t,
+            Self::SteeringBelow(t) => ctx.steering.abs() < *t,
+            Self::ClipFinished => ctx.animator.clip_finished,
+            Self::TimeInStateAbove(t) => ctx.animator.time_in_state > *t,
+            Self::Periodic(period) => {
+                let now = ctx.animator.elapsed;
+                *period > 0.0 && (now / period).floor() != ((now - ctx.dt) / period).floor()
+            }
+            Self::Behavior(label) => ctx.label == Some(label.as_str()),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn run_animators(
+    mut q: Query<(
+        &mut SpriteAnimator,
+        Option<&Velocity>,
+        Option<&Acceleration>,
+        Option<&Grounded>,
+        Option<&MovementControl>,
+        Option<&BehaviorLabel>,
+    )>,
+    handles: Res<SpriteAnimationGraphs>,
+    graphs: Res<Assets<SpriteAnimationGraph>>,
+    registry: Res<SpriteSheetRegistry>,
+    time: Res<SimulationClock>,
+) {
+    let dt = time.delta_seconds();
+    for (mut animator, vel, acc, grounded, mc, label) in q.iter_mut() {
+        let Some(graph) = handles.0.get(animator.graph).and_then(|h| graphs.get(h)) else {
+            continue;
+        };
+        let sheet = animator.sheet;
+        let clip_of = |state: &str| {
+            graph
+                .states
+                .get(state)
+                .and_then(|s| registry.clip(sheet, &s.clip))
+        };
+
+        // The graph may have been reloaded without the current state
+        let state_name = match animator.state.as_ref() {
+            Some(s) if graph.states.contains_key(s) => s.clone(),
+            _ => {
+                let Some(clip) = clip_of(&graph.initial) else {
+                    continue;
+                };
+                animator.enter(&graph.initial, clip);
+                graph.initial.clone()
+            }
+        };
+        let state = &graph.states[&state_name];
+        let velocity = vel.map(|v| v.0).unwrap_or(V2::new(0.0, 0.0));
+        let steering = mc.map(|mc| mc.0 .0.x).unwrap_or(0.0);
+
+        animator.time_in_state += dt;
+        animator.elapsed += dt;
+        if let Some(clip) = clip_of(&state_name) {
+            animator.advance(dt, clip, velocity.x.abs(), state.duration_per_speed);
+        }
+
+        let ctx = ConditionContext {
+            velocity,
+            acceleration: acc.map(|a| a.0).unwrap_or(V2::new(0.0, 0.0)),
+            grounded: matches!(grounded, Some(Grounded(GroundedState::GroundedTo(_)))),
+            steering,
+            label: label.map(|l| l.0),
+            animator: &*animator,
+            dt,
+        };
+        let taken = graph
+            .any
+            .iter()
+            .chain(state.transitions.iter())
+            .find(|t| t.when.iter().all(|c| c.holds(&ctx)));
+
+        if let Some(t) = taken {
+            if t.to != state_name || t.restart {
+                if let Some(clip) = clip_of(&t.to) {
+                    animator.enter(&t.to, clip);
+                }
+            }
+        }
+
+        let flip = animator
+            .state
+            .as_ref()
+            .and_then(|s| graph.states.get(s))
+            .and_then(|s| s.flip)
+            .unwrap_or(graph.flip);
+        let facing = match flip {
+            Flip::None => 0.0,
+            Flip::Velocity => velocity.x,
+            Flip::Control => steering,
+        };
+        if facing > 0.1 {
+            animator.flip_x = false;
+        } else if facing < -0.1 {
+            animator.flip_x = true;
+        }
+    }
+}
+
+fn apply_animators(mut q: Query<(&SpriteAnimator, &mut TextureAtlasSprite)>) {
+    for (animator, mut tas) in q.iter_mut() {
+        if animator.state.is_some() {
+            tas.index = animator.frame;
+            tas.flip_x = animator.flip_x;
+        }
+    }
+}
@@ -1,11 +1,12 @@
 pub mod eggman_animation;
+pub mod graph;
 pub mod pter_animation;
 pub mod rider_animation;
-mod pop;
+pub mod pop;
 
-use crate::engine::physics::Velocity;
 use crate::engine::physics::{PhysicsStages};
 use bevy::prelude::*;
+use crate::engine::simulation_clock::SimulationClock;
 
 pub struct AnimationPlugin;
 impl Plugin for AnimationPlugin {
@@ -14,7 +15,7 @@ impl Plugin for AnimationPlugin {
         app.add_systems(Update, 
             (
                 tick_animation_timers.in_set(CalculateNextStates),
-                (apply_frames, flip_sprites).in_set(ApplyAnimationState)
+                apply_frames.in_set(ApplyAnimationState)
             ),
         );
         app.configure_sets(Update, (
@@ -25,7 +26,7 @@ impl Plugin for AnimationPlugin {
         ).chain());
 
         app.add_plugins(
-            (pter_animation::PterAnimationPlugin,
+            (graph::SpriteAnimationGraphPlugin,
             rider_animation::RiderAnimationPlugin,
             eggman_animation::EggmanAnimationPlugin,
             pop::PopAnimationPlugin,)
@@ -38,11 +39,6 @@ impl Plugin for AnimationPlugin {
 #[derive(Component, Deref, DerefMut)]
 pub struct Frame(usize);
 
-/// Insert this component into an entity with a TextureAtlasSprite and Velocity to get it to flip
-/// every frame
-#[derive(Component)]
-pub struct FlipSpriteSheetBasedOnVelocity;
-
 /// If you want to manually set the frame of an animatable,
 /// set it between the CalculateNextStates and ApplyAnimationState
 #[derive(SystemSet, Clone, Copy, Hash, Debug, PartialEq, Eq)]
@@ -56,7 +52,7 @@ pub enum AnimationStages {
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
 
-fn tick_animation_timers(mut q: Query<&mut AnimationTimer>, time: Res<Time>) {
+fn tick_animation_timers(mut q: Query<&mut AnimationTimer>, time: Res<SimulationClock>) {
     for mut timer in q.iter_mut() {
         timer.0.tick(time.delta());
     }
@@ -67,15 +63,3 @@ fn apply_frames(mut q: Query<(&Frame, &mut TextureAtlasSprite)>) {
         tas.index = **f;
     }
 }
-
-fn flip_sprites(
-    mut q: Query<(&mut TextureAtlasSprite, &Velocity), With<FlipSpriteSheetBasedOnVelocity>>,
-) {
-    for (mut tas, vel) in q.iter_mut() {
-        if vel.0.x > 0.1 {
-            tas.flip_x = false;
-        } else if vel.0.x < -0.1 {
-            tas.flip_x = true;
-        }
-    }
-}
@@ -1,11 +1,16 @@
 use bevy::prelude::*;
 use crate::animation::{AnimationTimer, Frame, AnimationStages};
-use crate::entities::spritesheets::PopTextureAtlas;
+use crate::entities::spritesheets::SpriteSheetRegistry;
 
 pub struct PopAnimationPlugin;
 impl Plugin for PopAnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, animate_pop.in_set(AnimationStages::CalculateNextStates));
+        app.add_systems(
+            Update,
+            animate_pop
+                .in_set(AnimationStages::CalculateNextStates)
+                .run_if(resource_exists::<SpriteSheetRegistry>()),
+        );
     }
 }
 
@@ -29,12 +34,18 @@ impl Default for PopAnimationBundle {
     }
 }
 
-fn animate_pop(mut q: Query<(&mut Frame, &AnimationTimer), With<Pop>>) {
+fn animate_pop(
+    mut q: Query<(&mut Frame, &AnimationTimer), With<Pop>>,
+    registry: Res<SpriteSheetRegistry>,
+) {
+    let Some(clip) = registry.clip("pop", "pop") else {
+        return;
+    };
     for (mut f, at) in q.iter_mut() {
         if at.finished() {
             f.0 += 1;
-            if f.0 == 9 {
-                f.0 = 0;
+            if f.0 > clip.last {
+                f.0 = clip.first;
             }
         }
     }
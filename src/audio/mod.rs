@@ -0,0 +1,33 @@
+/*
+Sound
+
+Set JOUST_NO_AUDIO to any value other than 0 to run without sound, for
+example on machines without an audio device. bevy's own AudioPlugin is
+left out as well, see main.
+*/
+
+pub mod sfx;
+pub mod synth;
+
+use bevy::prelude::*;
+
+const NO_AUDIO_VAR: &str = "JOUST_NO_AUDIO";
+
+/// False when JOUST_NO_AUDIO is set
+pub fn audio_enabled() -> bool {
+    match std::env::var(NO_AUDIO_VAR) {
+        Ok(v) => v.is_empty() || v == "0",
+        Err(_) => true,
+    }
+}
+
+pub struct JoustAudioPlugin;
+impl Plugin for JoustAudioPlugin {
+    fn build(&self, app: &mut App) {
+        if !audio_enabled() {
+            info!(target: "joust::audio", "{} is set, running without sound", NO_AUDIO_VAR);
+            return;
+        }
+        app.add_plugins((synth::SynthPlugin, sfx::SfxPlugin));
+    }
+}
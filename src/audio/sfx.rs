@@ -0,0 +1,337 @@
+/*
+Sound effects
+
+Gameplay is watched for the moments that make a sound, each is sent as a
+PlaySfx event:
+    Flap: a rider enters the downflap animation state
+    Skid: a rider enters the drifting animation state
+    Clash: two lances of the same height bounce apart
+    Unseat: a rider is unseated
+    EggLand: an egg touches a platform after falling
+    EggHatch: an egg starts hatching
+    EggCollect: a player crushes an egg
+    Pop: an eggman pops
+    Screech: a pterodactyl enters the arena or starts a dive
+
+Each effect is played from `assets/sounds/<name>.ogg` or `.wav`. Effects
+without a file use the synthesized preset of the same name from
+`assets/sounds/joust.sfx.ron`, see audio::synth. Effects with neither stay
+silent.
+
+The volume of an effect is the sfx volume setting times the volume of its
+category in SfxMix, bevy's GlobalVolume takes care of the master volume.
+Only MAX_VOICES effects play at once, and only MAX_VOICES_PER_EFFECT of the
+same effect, so twenty eggs hatching together are not twenty times as loud.
+*/
+
+use crate::animation::pop::Pop;
+use crate::audio::synth::SynthBank;
+use crate::animation::rider_animation::RiderAnimationState;
+use crate::behavior::eggman_control::EggmanState;
+use crate::behavior::pter_control::PterControlMovementState;
+use crate::engine::collision::{CalculateCollisions, StaticCollisionEvent};
+use crate::entities::eggman::Eggman;
+use crate::game_state::arena_running;
+use crate::entities::pter::PterSprite;
+use crate::player::player_damage::JoustEvent;
+use crate::settings::Settings;
+use bevy::asset::io::file::FileAssetReader;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+const PRESETS_PATH: &str = "sounds/joust.sfx.ron";
+const MAX_VOICES: usize = 8;
+const MAX_VOICES_PER_EFFECT: usize = 3;
+
+pub struct SfxPlugin;
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySfx>();
+        app.init_resource::<SfxMix>();
+        app.init_resource::<SoundBank>();
+        app.add_systems(Startup, load_sounds);
+        app.add_systems(
+            Update,
+            (
+                use_synth_sounds,
+                (
+                    rider_sounds,
+                    joust_sounds,
+                    // Only while collisions are calculated, so every frame it reads has its batch
+                    egg_land_sounds.after(CalculateCollisions).run_if(arena_running),
+                    eggman_sounds,
+                    pop_sounds,
+                    pter_sounds,
+                ),
+                play_sfx,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Flap,
+    Skid,
+    Clash,
+    Unseat,
+    EggLand,
+    EggHatch,
+    EggCollect,
+    Pop,
+    Screech,
+}
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 9] = [
+        SoundEffect::Flap,
+        SoundEffect::Skid,
+        SoundEffect::Clash,
+        SoundEffect::Unseat,
+        SoundEffect::EggLand,
+        SoundEffect::EggHatch,
+        SoundEffect::EggCollect,
+        SoundEffect::Pop,
+        SoundEffect::Screech,
+    ];
+
+    /// The file name of the effect, without the extension
+    pub fn name(&self) -> &'static str {
+        match self {
+            SoundEffect::Flap => "flap",
+            SoundEffect::Skid => "skid",
+            SoundEffect::Clash => "clash",
+            SoundEffect::Unseat => "unseat",
+            SoundEffect::EggLand => "egg_land",
+            SoundEffect::EggHatch => "egg_hatch",
+            SoundEffect::EggCollect => "egg_collect",
+            SoundEffect::Pop => "pop",
+            SoundEffect::Screech => "screech",
+        }
+    }
+
+    pub fn category(&self) -> SfxCategory {
+        match self {
+            SoundEffect::Flap | SoundEffect::Skid => SfxCategory::Riders,
+            SoundEffect::Clash | SoundEffect::Unseat => SfxCategory::Combat,
+            SoundEffect::EggLand
+            | SoundEffect::EggHatch
+            | SoundEffect::EggCollect
+            | SoundEffect::Pop => SfxCategory::Eggs,
+            SoundEffect::Screech => SfxCategory::Enemies,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SfxCategory {
+    Riders,
+    Combat,
+    Eggs,
+    Enemies,
+}
+
+/// Volume of each category, from 0 to 1
+#[derive(Resource)]
+pub struct SfxMix {
+    pub categories: HashMap<SfxCategory, f32>,
+}
+impl Default for SfxMix {
+    fn default() -> Self {
+        Self {
+            categories: HashMap::from([
+                (SfxCategory::Riders, 0.6),
+                (SfxCategory::Combat, 1.0),
+                (SfxCategory::Eggs, 0.8),
+                (SfxCategory::Enemies, 0.9),
+            ]),
+        }
+    }
+}
+impl SfxMix {
+    pub fn volume(&self, category: SfxCategory) -> f32 {
+        self.categories.get(&category).copied().unwrap_or(1.0)
+    }
+}
+
+/// The sound played for each effect
+#[derive(Resource, Default)]
+pub struct SoundBank {
+    pub sounds: HashMap<SoundEffect, Handle<AudioSource>>,
+    /// Effects played from a sound file rather than a preset
+    from_files: HashSet<SoundEffect>,
+}
+
+#[derive(Resource)]
+struct SynthBankHandle(Handle<SynthBank>);
+
+/// Plays `0` unless too many sounds are playing already
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlaySfx(pub SoundEffect);
+
+/// A sound effect that is playing
+#[derive(Component)]
+struct SfxVoice(SoundEffect);
+
+fn load_sounds(mut commands: Commands, mut bank: ResMut<SoundBank>, asset_server: Res<AssetServer>) {
+    let assets = FileAssetReader::get_base_path().join("assets");
+    for effect in SoundEffect::ALL {
+        let path = ["ogg", "wav"]
+            .iter()
+            .map(|ext| format!("sounds/{}.{}", effect.name(), ext))
+            .find(|p| assets.join(p).exists());
+        match path {
+            Some(p) => {
+                bank.sounds.insert(effect, asset_server.load(p));
+                bank.from_files.insert(effect);
+            }
+            None => debug!(target: "joust::audio", "No sound file for {:?}", effect),
+        }
+    }
+    commands.insert_resource(SynthBankHandle(asset_server.load(PRESETS_PATH)));
+}
+
+/// Fills the effects without a sound file from the presets, again when they change
+fn use_synth_sounds(
+    mut events: EventReader<AssetEvent<SynthBank>>,
+    handle: Res<SynthBankHandle>,
+    synth_banks: Res<Assets<SynthBank>>,
+    mut bank: ResMut<SoundBank>,
+) {
+    let changed = events.read().any(|e| match e {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => *id == handle.0.id(),
+        _ => false,
+    });
+    if !changed {
+        return;
+    }
+    let Some(synth) = synth_banks.get(&handle.0) else {
+        return;
+    };
+    for effect in SoundEffect::ALL {
+        if bank.from_files.contains(&effect) {
+            continue;
+        }
+        match synth.sounds.get(effect.name()) {
+            Some(sound) => {
+                bank.sounds.insert(effect, sound.clone());
+            }
+            None => {
+                bank.sounds.remove(&effect);
+                debug!(target: "joust::audio", "No sound preset for {:?}", effect);
+            }
+        }
+    }
+    info!(target: "joust::audio", "Synthesized {} sound presets", synth.sounds.len());
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    mut events: EventReader<PlaySfx>,
+    bank: Res<SoundBank>,
+    mix: Res<SfxMix>,
+    settings: Res<Settings>,
+    q_voices: Query<&SfxVoice>,
+) {
+    let mut voices = q_voices.iter().count();
+    let mut per_effect = HashMap::<SoundEffect, usize>::new();
+    for voice in q_voices.iter() {
+        *per_effect.entry(voice.0).or_default() += 1;
+    }
+    // The same effect twice in one frame would only sound louder
+    let mut played = HashSet::new();
+
+    for PlaySfx(effect) in events.read() {
+        if voices >= MAX_VOICES {
+            break;
+        }
+        let playing = per_effect.entry(*effect).or_default();
+        if *playing >= MAX_VOICES_PER_EFFECT || !played.insert(*effect) {
+            continue;
+        }
+        let Some(source) = bank.sounds.get(effect) else {
+            continue;
+        };
+        let volume = settings.sfx_volume * mix.volume(effect.category());
+        commands.spawn((
+            AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+            },
+            SfxVoice(*effect),
+        ));
+        *playing += 1;
+        voices += 1;
+    }
+}
+
+fn rider_sounds(q: Query<Ref<RiderAnimationState>>, mut sfx: EventWriter<PlaySfx>) {
+    for state in q.iter() {
+        if !state.is_changed() || state.is_added() {
+            continue;
+        }
+        match *state {
+            RiderAnimationState::Downflap => sfx.send(PlaySfx(SoundEffect::Flap)),
+            RiderAnimationState::Drifting => sfx.send(PlaySfx(SoundEffect::Skid)),
+            _ => {}
+        }
+    }
+}
+
+fn joust_sounds(mut events: EventReader<JoustEvent>, mut sfx: EventWriter<PlaySfx>) {
+    for e in events.read() {
+        let effect = match e {
+            JoustEvent::Bounce { .. } => SoundEffect::Clash,
+            JoustEvent::EnemyUnseated { .. } | JoustEvent::PlayerUnseated { .. } => SoundEffect::Unseat,
+            JoustEvent::EggCrushed { .. } => SoundEffect::EggCollect,
+        };
+        sfx.send(PlaySfx(effect));
+    }
+}
+
+/// An egg lands when it touches a platform it was not touching last frame
+fn egg_land_sounds(
+    mut events: EventReader<StaticCollisionEvent>,
+    q_eggs: Query<&EggmanState, With<Eggman>>,
+    mut touching: Local<HashSet<(Entity, Entity)>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let mut now = HashSet::new();
+    for e in events.read() {
+        if let Ok(EggmanState::Egg | EggmanState::JustSpawned) = q_eggs.get(e.e1) {
+            now.insert((e.e1, e.e2));
+        }
+    }
+    if now.iter().any(|e| !touching.contains(e)) {
+        sfx.send(PlaySfx(SoundEffect::EggLand));
+    }
+    *touching = now;
+}
+
+fn eggman_sounds(q: Query<Ref<EggmanState>>, mut sfx: EventWriter<PlaySfx>) {
+    for state in q.iter() {
+        if state.is_changed() && matches!(*state, EggmanState::Hatching) {
+            sfx.send(PlaySfx(SoundEffect::EggHatch));
+        }
+    }
+}
+
+fn pop_sounds(q: Query<(), Added<Pop>>, mut sfx: EventWriter<PlaySfx>) {
+    if !q.is_empty() {
+        sfx.send(PlaySfx(SoundEffect::Pop));
+    }
+}
+
+fn pter_sounds(
+    q_new: Query<(), Added<PterSprite>>,
+    q_dive: Query<&PterControlMovementState, Changed<PterControlMovementState>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let diving = q_dive
+        .iter()
+        .any(|s| matches!(s, PterControlMovementState::Diving));
+    if !q_new.is_empty() || diving {
+        sfx.send(PlaySfx(SoundEffect::Screech));
+    }
+}
@@ -0,0 +1,274 @@
+/*
+A small retro sound synthesizer, in the spirit of sfxr
+
+A SynthPreset describes a sound:
+    a waveform: square, sawtooth, triangle, sine or noise
+    a pitch that slides up or down, with vibrato on top
+    an amount of noise mixed in
+    an envelope: attack, sustain with an optional punch, then decay
+
+Presets are kept in `assets/sounds/joust.sfx.ron`, by name. The loader turns
+every preset into a 16 bit mono WAV AudioSource, a labeled asset of the
+SynthBank, so `sounds/joust.sfx.ron#flap` is the flap sound. Editing the file
+while the game runs rebuilds the sounds.
+
+The noise is seeded from the preset, so a preset always makes the same sound.
+*/
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+pub const SAMPLE_RATE: u32 = 44100;
+
+pub struct SynthPlugin;
+impl Plugin for SynthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SynthBank>();
+        app.init_asset_loader::<SynthBankLoader>();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    Square,
+    Sawtooth,
+    Triangle,
+    Sine,
+    Noise,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SynthPreset {
+    pub waveform: Waveform,
+    /// Part of each square wave period that is high, from 0 to 1
+    pub duty: f32,
+    /// Starting pitch in Hz
+    pub frequency: f32,
+    /// Octaves per second, negative slides down
+    pub slide: f32,
+    /// The pitch does not slide below this, in Hz
+    pub min_frequency: f32,
+    /// Part of the pitch that the vibrato moves it by
+    pub vibrato_depth: f32,
+    /// Vibrato cycles per second
+    pub vibrato_speed: f32,
+    /// White noise mixed into the wave, from 0 to 1
+    pub noise: f32,
+    /// Seconds to fade in
+    pub attack: f32,
+    /// Seconds at full volume
+    pub sustain: f32,
+    /// Extra volume at the start of the sustain, fading over it
+    pub punch: f32,
+    /// Seconds to fade out
+    pub decay: f32,
+    pub volume: f32,
+    pub seed: u64,
+}
+impl Default for SynthPreset {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            duty: 0.5,
+            frequency: 440.0,
+            slide: 0.0,
+            min_frequency: 20.0,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            noise: 0.0,
+            attack: 0.0,
+            sustain: 0.1,
+            punch: 0.0,
+            decay: 0.1,
+            volume: 0.5,
+            seed: 1,
+        }
+    }
+}
+impl SynthPreset {
+    /// Seconds from the start of the attack to the end of the decay
+    pub fn duration(&self) -> f32 {
+        self.attack.max(0.0) + self.sustain.max(0.0) + self.decay.max(0.0)
+    }
+
+    fn envelope(&self, t: f32) -> f32 {
+        let (attack, sustain, decay) = (self.attack.max(0.0), self.sustain.max(0.0), self.decay.max(0.0));
+        if t < attack {
+            t / attack
+        } else if t < attack + sustain {
+            1.0 + self.punch * (1.0 - (t - attack) / sustain)
+        } else if decay > 0.0 {
+            (1.0 - (t - attack - sustain) / decay).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// The samples of the sound, from -1 to 1
+    pub fn synthesize(&self, sample_rate: u32) -> Vec<f32> {
+        let rate = sample_rate as f32;
+        let len = (self.duration() * rate).round() as usize;
+        let mut rng = XorShift::new(self.seed);
+        let mut phase = 0.0_f32;
+        // Noise holds a random value for each half period, like sfxr
+        let mut noise_value = rng.next_f32();
+
+        let mut samples = Vec::with_capacity(len);
+        for i in 0..len {
+            let t = i as f32 / rate;
+            let vibrato = 1.0 + self.vibrato_depth * (TAU * self.vibrato_speed * t).sin();
+            let frequency = (self.frequency * 2.0_f32.powf(self.slide * t)).max(self.min_frequency) * vibrato;
+
+            let last_phase = phase;
+            phase = (phase + frequency / rate).fract();
+            if self.waveform == Waveform::Noise && (phase < last_phase || (last_phase < 0.5 && phase >= 0.5)) {
+                noise_value = rng.next_f32();
+            }
+
+            let wave = match self.waveform {
+                Waveform::Square => {
+                    if phase < self.duty {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Waveform::Sawtooth => 2.0 * phase - 1.0,
+                Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                Waveform::Sine => (TAU * phase).sin(),
+                Waveform::Noise => noise_value,
+            };
+            let noise = self.noise.clamp(0.0, 1.0);
+            let sample = wave * (1.0 - noise) + rng.next_f32() * noise;
+            samples.push((sample * self.envelope(t) * self.volume).clamp(-1.0, 1.0));
+        }
+        samples
+    }
+}
+
+/// Encodes samples as a 16 bit mono WAV file
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16_u32.to_le_bytes());
+    // PCM, one channel
+    wav.extend_from_slice(&1_u16.to_le_bytes());
+    wav.extend_from_slice(&1_u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    // Bytes per frame, bits per sample
+    wav.extend_from_slice(&2_u16.to_le_bytes());
+    wav.extend_from_slice(&16_u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        wav.extend_from_slice(&((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
+
+/// A tiny seeded random generator, so sounds do not change between runs
+struct XorShift(u64);
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // Zero would only ever give zero
+        Self(seed.max(1))
+    }
+
+    /// From -1 to 1
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+/// The sounds made from a presets file, by preset name
+#[derive(Asset, TypePath)]
+pub struct SynthBank {
+    pub sounds: HashMap<String, Handle<AudioSource>>,
+}
+
+#[derive(Deserialize)]
+struct SynthBankDef {
+    presets: HashMap<String, SynthPreset>,
+}
+
+#[derive(Default)]
+pub struct SynthBankLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SynthBankLoaderError {
+    #[error("Could not read the sound presets: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the sound presets: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for SynthBankLoader {
+    type Asset = SynthBank;
+    type Settings = ();
+    type Error = SynthBankLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SynthBank, SynthBankLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let def = ron::de::from_bytes::<SynthBankDef>(&bytes)?;
+
+            let mut sounds = HashMap::new();
+            for (name, preset) in def.presets {
+                let wav = encode_wav(&preset.synthesize(SAMPLE_RATE), SAMPLE_RATE);
+                let source = AudioSource { bytes: wav.into() };
+                sounds.insert(name.clone(), load_context.add_labeled_asset(name, source));
+            }
+            Ok(SynthBank { sounds })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sfx.ron"]
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_synthesize() {
+    let preset = SynthPreset {
+        waveform: Waveform::Noise,
+        noise: 0.3,
+        vibrato_depth: 0.1,
+        vibrato_speed: 8.0,
+        slide: -2.0,
+        attack: 0.01,
+        sustain: 0.05,
+        punch: 0.5,
+        decay: 0.14,
+        ..Default::default()
+    };
+    let samples = preset.synthesize(SAMPLE_RATE);
+    assert_eq!(samples.len(), (0.2 * SAMPLE_RATE as f32).round() as usize);
+    assert!(samples.iter().all(|s| (-1.0..=1.0).contains(s)));
+    assert_eq!(samples, preset.synthesize(SAMPLE_RATE));
+
+    let reseeded = SynthPreset { seed: 2, ..preset.clone() };
+    assert_ne!(samples, reseeded.synthesize(SAMPLE_RATE));
+
+    let wav = encode_wav(&samples, SAMPLE_RATE);
+    assert_eq!(wav.len(), 44 + samples.len() * 2);
+    assert_eq!(&wav[0..4], b"RIFF");
+}
@@ -9,23 +9,24 @@ Lifecycle is as follows:
 
 Each section of the lifecycle is timed, as determined by each eggman's
 EggmanLifecycleCharacteristics component.
+
+The lifecycle is the tree in `assets/behaviors/eggman.bt.ron`, see
+behavior::tree. Its state is kept in the EggmanState.
 */
 
 use crate::behavior::movement_control::MovementControl;
-use crate::engine::collision::{NonStaticCollisionEvent, ShouldCalculateNonStaticIntersectionsOn};
-use crate::engine::physics::Position;
+use crate::behavior::tree::{BehaviorTreeBundle, BehaviorTreeSystems, Blackboard};
+use crate::behavior::BehaviorSystems;
+use crate::engine::collision::ShouldCalculateNonStaticIntersectionsOn;
 use crate::engine::speed_clamps::SpeedClamps;
-use crate::entities::eggman::Eggman;
-use crate::player::player_control::PlayerController;
 use bevy::prelude::*;
-use cgmath::InnerSpace;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
 
 #[derive(Bundle)]
 pub struct EggmanControlBundle {
     mc: MovementControl,
     es: EggmanState,
-    bt: BehaviorTimer,
+    tree: BehaviorTreeBundle,
     ebc: EggmanLifecycleCharacteristics,
     esc: EggmanSpeedCharacteristics,
     speed_clamps: SpeedClamps,
@@ -36,15 +37,19 @@ impl Default for EggmanControlBundle {
         Self {
             mc: MovementControl::default(),
             es: EggmanState::JustSpawned,
-            bt: BehaviorTimer(Timer::from_seconds(10.0, TimerMode::Once)),
+            tree: BehaviorTreeBundle::new("eggman", EggmanState::JustSpawned.label()),
             ebc: EggmanLifecycleCharacteristics::default(),
             esc: EggmanSpeedCharacteristics::default(),
-            speed_clamps: SpeedClamps::new_from_x_y(3.0, 10.0),
+            speed_clamps: default_eggman_speed_clamps(),
             should: ShouldCalculateNonStaticIntersectionsOn,
         }
     }
 }
 
+pub fn default_eggman_speed_clamps() -> SpeedClamps {
+    SpeedClamps::new_from_x_y(3.0, 10.0)
+}
+
 /// Controls characterisitcs of this eggman's lifecycle
 /// in seconds
 #[derive(Component)]
@@ -63,9 +68,6 @@ impl Default for EggmanLifecycleCharacteristics {
     }
 }
 
-#[derive(Component, Deref, DerefMut)]
-struct BehaviorTimer(Timer);
-
 #[derive(Component)]
 pub enum EggmanState {
     JustSpawned,
@@ -75,6 +77,34 @@ pub enum EggmanState {
     /// The Eggman is going after the player
     Seeking,
 }
+impl EggmanState {
+    /// Name of the state, as used by the eggman animation graph
+    pub fn label(&self) -> &'static str {
+        match self {
+            EggmanState::JustSpawned => "JustSpawned",
+            EggmanState::Egg => "Egg",
+            EggmanState::Hatching => "Hatching",
+            EggmanState::Hatched => "Hatched",
+            EggmanState::Seeking => "Seeking",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "JustSpawned" => Some(EggmanState::JustSpawned),
+            "Egg" => Some(EggmanState::Egg),
+            "Hatching" => Some(EggmanState::Hatching),
+            "Hatched" => Some(EggmanState::Hatched),
+            "Seeking" => Some(EggmanState::Seeking),
+            _ => None,
+        }
+    }
+
+    /// Eggs can be crushed by a player until the eggman inside hatches
+    pub fn is_crushable(&self) -> bool {
+        !matches!(self, EggmanState::Hatched | EggmanState::Seeking)
+    }
+}
 
 #[derive(Component)]
 pub struct DeadEggman;
@@ -82,58 +112,18 @@ pub struct DeadEggman;
 pub struct EggmanControlPlugin;
 impl Plugin for EggmanControlPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update,(advance_lifecycle, control_eggman));
+        app.add_systems(
+            Update,
+            (
+                fill_blackboards.before(BehaviorTreeSystems),
+                read_states.after(BehaviorTreeSystems),
+            )
+                .in_set(BehaviorSystems),
+        );
     }
 }
 
-fn advance_lifecycle(
-    mut q: Query<(
-        &mut BehaviorTimer,
-        &mut EggmanState,
-        &EggmanLifecycleCharacteristics,
-    )>,
-    time: Res<Time>,
-) {
-    use EggmanState::*;
-
-    for (mut bt, mut es, elc) in q.iter_mut() {
-        bt.tick(time.delta());
-        match es.as_ref() {
-            JustSpawned => {
-                bt.set_duration(Duration::from_secs_f32(elc.incubation_period));
-                bt.reset();
-                *es = Egg;
-                println!("Spawned");
-            }
-            Egg => {
-                if bt.finished() {
-                    // Starts Hatching this egg this frame
-                    *es = Hatching;
-                    bt.set_duration(Duration::from_secs_f32(elc.hatch_period_time));
-                    bt.reset();
-                    println!("Hatching");
-                }
-            }
-            Hatching => {
-                if bt.finished() {
-                    *es = Hatched;
-                    bt.set_duration(Duration::from_secs_f32(elc.hatched_period_time));
-                    bt.reset();
-                    println!("Hatched!");
-                }
-            }
-            Hatched => {
-                if bt.finished() {
-                    *es = Seeking;
-                    println!("Seeking");
-                }
-            }
-            Seeking => {}
-        }
-    }
-}
-
-#[derive(Component)]
+#[derive(Component, Copy, Clone, Serialize, Deserialize)]
 pub struct EggmanSpeedCharacteristics {
     pub hor_accel: f32,
     pub vert_accel: f32,
@@ -147,53 +137,23 @@ impl Default for EggmanSpeedCharacteristics {
     }
 }
 
-fn control_eggman(
-    mut q: Query<(
-        &mut MovementControl,
-        &Position,
-        &EggmanSpeedCharacteristics,
-        &EggmanState,
-    )>,
-    q_player: Query<&Position, With<PlayerController>>,
+fn fill_blackboards(
+    mut q: Query<(&EggmanLifecycleCharacteristics, &EggmanSpeedCharacteristics, &mut Blackboard)>,
 ) {
-    // TODO Only works for a single player.
-    if let Ok(player_pos) = q_player.get_single() {
-        for (mut mc, pos, esc, es) in q.iter_mut() {
-            // Only controls movement if this eggman is in the seeking state
-            match es {
-                EggmanState::Seeking => {
-                    let direction = (player_pos.0 - pos.0).normalize();
-
-                    mc.0 .0.x = direction.x * esc.hor_accel;
-                }
-                _ => {}
-            }
-        }
+    for (elc, esc, mut bb) in q.iter_mut() {
+        bb.set_value("incubation_period", elc.incubation_period);
+        bb.set_value("hatch_period_time", elc.hatch_period_time);
+        bb.set_value("hatched_period_time", elc.hatched_period_time);
+        bb.set_value("hor_accel", esc.hor_accel);
     }
 }
 
-fn handle_collision(
-    mut commands: Commands,
-    q: Query<(Entity, &EggmanState, &NonStaticCollisionEvent)>,
-    q_player: Query<&Position, With<PlayerController>>,
-) {
-    for (ent, eggman_state, coll_event) in q.iter() {
-        //commands.entity(ent).remove::<NonStaticCollisionEvent>();
-
-        match eggman_state {
-            EggmanState::Hatched => {}
-            EggmanState::Seeking => {}
-            _ => {
-                // Eggman is in a crushable state
-                match q_player.get(coll_event.0) {
-                    Ok(pos) => {
-                        // Eggman is colliding with the player,
-                        // Eggman gets crushed
-                        commands.entity(ent).insert(DeadEggman);
-                    }
-                    Err(_) => {}
-                }
-            }
+fn read_states(mut q: Query<(&Blackboard, &mut EggmanState)>) {
+    for (bb, mut es) in q.iter_mut() {
+        match EggmanState::from_label(&bb.state) {
+            Some(state) if state.label() != es.label() => *es = state,
+            Some(_) => {}
+            None => warn!(target: "joust::behavior", "Eggmen have no {} state", bb.state),
         }
     }
 }
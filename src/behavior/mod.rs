@@ -2,10 +2,16 @@ use bevy::{app::PluginGroupBuilder, prelude::*};
 use eggman_control::EggmanControlPlugin;
 use movement_control::MovementControlPlugin;
 use pter_control::PterControlPlugin;
+use tree::BehaviorTreePlugin;
 
 pub mod eggman_control;
 pub mod movement_control;
 pub mod pter_control;
+pub mod tree;
+
+/// Every Update system of the behavior plugins, skipped outside of the arena
+#[derive(SystemSet, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct BehaviorSystems;
 
 pub struct BehaviorPlugins;
 impl PluginGroup for BehaviorPlugins {
@@ -15,5 +21,6 @@ impl PluginGroup for BehaviorPlugins {
             .add(PterControlPlugin)
             .add(EggmanControlPlugin)
             .add(MovementControlPlugin)
+            .add(BehaviorTreePlugin)
     }
 }
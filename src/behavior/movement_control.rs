@@ -1,12 +1,16 @@
 use bevy::prelude::*;
 use crate::engine::physics::{V2, PhysicsStages, Force, Mass, Acceleration};
+use crate::behavior::BehaviorSystems;
 
 /// Add this plugin to let ai and players control the movement of entities
 pub struct MovementControlPlugin;
 impl Plugin for MovementControlPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, 
-            apply_movement.in_set(PhysicsStages::CalculateNextForces).after(PhysicsStages::ClearNextForces));
+            apply_movement
+                .in_set(PhysicsStages::CalculateNextForces)
+                .after(PhysicsStages::ClearNextForces)
+                .in_set(BehaviorSystems));
     }
 }
 
@@ -1,44 +1,56 @@
 /*
 Steers this Pter towards the Player controlled rider
-*/
-use std::time::Duration;
 
-use crate::{engine::physics::{Position, Velocity, V2}, entities::pter::*, player::player_control::PlayerController};
+The behavior is the tree in `assets/behaviors/pter.bt.ron`, see
+behavior::tree. Its state is kept in the PterControlMovementState.
+*/
 use crate::engine::speed_clamps::SpeedClamps;
 
 use bevy::prelude::*;
-use cgmath::InnerSpace;
+use serde::{Deserialize, Serialize};
 
 use super::movement_control::MovementControl;
+use super::tree::{BehaviorTreeBundle, BehaviorTreeSystems, Blackboard};
+use super::BehaviorSystems;
 
 #[derive(Bundle)]
 pub struct PterControlBundle {
     pub mc: MovementControl,
-    dt: DiveTimer,
-    ft: FlapTimer,
+    tree: BehaviorTreeBundle,
     pcms: PterControlMovementState,
     psc: PterSpeedCharacteristics,
     speed_clamps: SpeedClamps,
 }
 impl Default for PterControlBundle {
     fn default() -> Self {
-        let max_horiz_speed = 10.0;
-        let max_vert_speed = 10.0;
         Self {
             mc: MovementControl::default(),
-            dt: DiveTimer(Timer::new(Duration::from_secs_f32(8.0), TimerMode::Once)),
-            ft: FlapTimer(Timer::new(Duration::from_secs_f32(3.0), TimerMode::Once)),
+            tree: BehaviorTreeBundle::new("pter", PterControlMovementState::default().label()),
             pcms: PterControlMovementState::default(),
             psc: PterSpeedCharacteristics::default(),
-            speed_clamps: SpeedClamps{pos_x: max_horiz_speed, neg_x: -max_horiz_speed, pos_y: max_vert_speed, neg_y: -1.0E20},
+            speed_clamps: default_pter_speed_clamps(),
         }
     }
 }
 
+/// Pters can dive as fast as they want
+pub fn default_pter_speed_clamps() -> SpeedClamps {
+    let max_horiz_speed = 10.0;
+    let max_vert_speed = 10.0;
+    SpeedClamps{pos_x: max_horiz_speed, neg_x: -max_horiz_speed, pos_y: max_vert_speed, neg_y: -1.0E20}
+}
+
 pub struct PterControlPlugin;
 impl Plugin for PterControlPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update,control_pter);
+        app.add_systems(
+            Update,
+            (
+                fill_blackboards.before(BehaviorTreeSystems),
+                read_states.after(BehaviorTreeSystems),
+            )
+                .in_set(BehaviorSystems),
+        );
     }
 }
 
@@ -53,11 +65,30 @@ impl Default for PterControlMovementState {
         Self::Coasting
     }
 }
+impl PterControlMovementState {
+    /// Name of the state, as used by the behavior tree
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Diving => "Diving",
+            Self::Flapping => "Flapping",
+            Self::Coasting => "Coasting",
+        }
+    }
 
-#[derive(Component)]
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "Diving" => Some(Self::Diving),
+            "Flapping" => Some(Self::Flapping),
+            "Coasting" => Some(Self::Coasting),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone, Serialize, Deserialize)]
 pub struct PterSpeedCharacteristics {
-    dive_force: f32,
-    flap_force: f32,
+    pub dive_force: f32,
+    pub flap_force: f32,
 }
 impl Default for PterSpeedCharacteristics {
     fn default() -> Self {
@@ -68,41 +99,18 @@ impl Default for PterSpeedCharacteristics {
     }
 }
 
-#[derive(Component)]
-struct DiveTimer(Timer);
-#[derive(Component)]
-struct FlapTimer(Timer);
-
-fn control_pter(
-    mut q: Query<(&mut MovementControl, &Position, &PterSpeedCharacteristics, &mut PterControlMovementState, &mut DiveTimer, &mut FlapTimer), With<PterSprite>>,
-    q_player: Query<(&Position), With<PlayerController>>,
-    time: Res<Time>,
-) {
-    // TODO only works with single player
-    if let Ok(player_pos) = q_player.get_single() {
-        for (mut mc, pos, psc, mut pcms, mut dt, mut ft) in q.iter_mut() {
-            dt.0.tick(time.delta());
-            ft.0.tick(time.delta());
-            // Direction to player
-            let direction = (player_pos.0 - pos.0).normalize();
-
-            // Should the pter dive? If it is below and the timer is up
-            if dt.0.finished() && direction.y < 0.0 {
-                *pcms = PterControlMovementState::Diving;
-                dt.0.reset();
-                mc.0.0.y = direction.y * 3.0;
-                mc.0.0.x = direction.x * 1.0;
-            }
+fn fill_blackboards(mut q: Query<(&PterSpeedCharacteristics, &mut Blackboard)>) {
+    for (psc, mut bb) in q.iter_mut() {
+        bb.set_value("flap_force", psc.flap_force);
+    }
+}
 
-            // Should the pter flap? If the player is above and the timer is up
-            else if ft.0.finished() {
-                *pcms = PterControlMovementState::Flapping;
-                ft.0.reset();
-                mc.0.0.y = psc.flap_force;
-            } else {
-                *pcms = PterControlMovementState::Coasting;
-            }
+fn read_states(mut q: Query<(&Blackboard, &mut PterControlMovementState)>) {
+    for (bb, mut pcms) in q.iter_mut() {
+        match PterControlMovementState::from_label(&bb.state) {
+            Some(state) if state.label() != pcms.label() => *pcms = state,
+            Some(_) => {}
+            None => warn!(target: "joust::behavior", "Pters have no {} state", bb.state),
         }
     }
 }
-
@@ -0,0 +1,368 @@
+/*
+Data driven behavior trees
+
+A behavior tree is loaded from `assets/behaviors/<name>.bt.ron`. It is made
+of four kinds of nodes:
+    Sequence: runs its children in order, until one of them fails
+    Selector: runs its children in order, until one of them succeeds
+    Condition: succeeds when its condition holds
+    Action: does something to the entity, and succeeds unless it can't
+
+Trees keep no state of their own, the whole tree is run from the root every
+frame. What a behavior has to remember between frames goes in the Blackboard
+of the entity: the name of the state it is in, timers that count up from
+when they were last reset, or from when the entity spawned, and values.
+Controllers write the values their tree reads into the blackboard, the
+speed characteristics of the entity for instance, and read the state back
+out, see behavior::pter_control.
+
+Insert a BehaviorTreeBundle to run a tree on an entity. The target of an
+entity is the nearest player controlled rider, and steering writes to its
+MovementControl.
+*/
+
+use crate::behavior::movement_control::MovementControl;
+use crate::behavior::BehaviorSystems;
+use crate::engine::physics::{Position, V2};
+use crate::engine::simulation_clock::SimulationClock;
+use crate::loading::RequiredAssets;
+use crate::player::player_control::PlayerController;
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use cgmath::{InnerSpace, MetricSpace};
+use serde::Deserialize;
+
+/// Trees loaded at startup, by name
+const TREES: [&str; 2] = ["pter", "eggman"];
+
+pub struct BehaviorTreePlugin;
+impl Plugin for BehaviorTreePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BehaviorTree>();
+        app.init_asset_loader::<BehaviorTreeLoader>();
+        app.add_systems(Startup, load_trees);
+        app.add_systems(
+            Update,
+            (find_targets, run_trees)
+                .chain()
+                .in_set(BehaviorTreeSystems)
+                .in_set(BehaviorSystems),
+        );
+    }
+}
+
+/// Trees are run in this set, controllers fill the blackboards before it and read them after it
+#[derive(SystemSet, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub struct BehaviorTreeSystems;
+
+/// A number, or the name of a value of the blackboard
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Scalar {
+    Const(f32),
+    Value(String),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum Condition {
+    /// The state of the blackboard is this
+    StateIs(String),
+    /// The timer has run for at least this many seconds
+    TimerElapsed(String, Scalar),
+    /// A player is there to go after
+    HasTarget,
+    TargetAbove,
+    TargetBelow,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum Action {
+    SetState(String),
+    ResetTimer(String),
+    /// Sets the movement control, on the axes that are given
+    Steer {
+        #[serde(default)]
+        x: Option<Scalar>,
+        #[serde(default)]
+        y: Option<Scalar>,
+    },
+    /// Sets the movement control to the direction of the target times these, on the axes that are given
+    /// Fails without a target
+    SteerToTarget {
+        #[serde(default)]
+        x: Option<Scalar>,
+        #[serde(default)]
+        y: Option<Scalar>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum Node {
+    Sequence(Vec<Node>),
+    Selector(Vec<Node>),
+    Condition(Condition),
+    Action(Action),
+}
+
+#[derive(Asset, TypePath, Clone, Debug, Deserialize)]
+pub struct BehaviorTree {
+    pub root: Node,
+}
+
+/// What the behavior of an entity remembers, and what it knows of the arena
+#[derive(Component, Clone, Debug)]
+pub struct Blackboard {
+    pub state: String,
+    /// Seconds the tree has been run for
+    pub time: f32,
+    /// When each timer was last reset
+    pub timers: HashMap<String, f32>,
+    pub values: HashMap<String, f32>,
+    /// Where the target is, when there is one
+    pub target: Option<V2>,
+}
+impl Blackboard {
+    pub fn new(state: &str) -> Self {
+        Self {
+            state: state.to_string(),
+            time: 0.0,
+            timers: HashMap::default(),
+            values: HashMap::default(),
+            target: None,
+        }
+    }
+
+    /// A value that was never written is 0
+    pub fn value(&self, name: &str) -> f32 {
+        self.values.get(name).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_value(&mut self, name: &str, value: f32) {
+        match self.values.get_mut(name) {
+            Some(v) => *v = value,
+            None => {
+                self.values.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// Seconds since the timer was reset
+    pub fn timer(&self, name: &str) -> f32 {
+        self.time - self.timers.get(name).copied().unwrap_or(0.0)
+    }
+
+    fn scalar(&self, scalar: &Scalar) -> f32 {
+        match scalar {
+            Scalar::Const(v) => *v,
+            Scalar::Value(name) => self.value(name),
+        }
+    }
+}
+
+/// Runs a behavior tree on this entity
+#[derive(Component)]
+pub struct BehaviorTreeRunner {
+    /// Name of the tree
+    tree: &'static str,
+}
+
+#[derive(Bundle)]
+pub struct BehaviorTreeBundle {
+    pub runner: BehaviorTreeRunner,
+    pub blackboard: Blackboard,
+}
+impl BehaviorTreeBundle {
+    /// Runs the tree `tree`, starting in the state `state`
+    pub fn new(tree: &'static str, state: &str) -> Self {
+        Self {
+            runner: BehaviorTreeRunner { tree },
+            blackboard: Blackboard::new(state),
+        }
+    }
+}
+
+/// The entity a tree is run for
+pub struct Agent<'a> {
+    pub blackboard: &'a mut Blackboard,
+    pub position: V2,
+    pub control: &'a mut V2,
+}
+
+impl Condition {
+    fn holds(&self, agent: &Agent) -> bool {
+        let bb = &agent.blackboard;
+        let direction_y = bb.target.map(|t| t.y - agent.position.y);
+        match self {
+            Self::StateIs(state) => bb.state == *state,
+            Self::TimerElapsed(timer, seconds) => bb.timer(timer) >= bb.scalar(seconds),
+            Self::HasTarget => bb.target.is_some(),
+            Self::TargetAbove => direction_y.is_some_and(|y| y > 0.0),
+            Self::TargetBelow => direction_y.is_some_and(|y| y < 0.0),
+        }
+    }
+}
+
+impl Action {
+    fn run(&self, agent: &mut Agent) -> bool {
+        match self {
+            Self::SetState(state) => agent.blackboard.state.clone_from(state),
+            Self::ResetTimer(timer) => {
+                let now = agent.blackboard.time;
+                agent.blackboard.timers.insert(timer.clone(), now);
+            }
+            Self::Steer { x, y } => {
+                if let Some(x) = x {
+                    agent.control.x = agent.blackboard.scalar(x);
+                }
+                if let Some(y) = y {
+                    agent.control.y = agent.blackboard.scalar(y);
+                }
+            }
+            Self::SteerToTarget { x, y } => {
+                let Some(target) = agent.blackboard.target else {
+                    return false;
+                };
+                if target == agent.position {
+                    return true;
+                }
+                let direction = (target - agent.position).normalize();
+                if let Some(x) = x {
+                    agent.control.x = direction.x * agent.blackboard.scalar(x);
+                }
+                if let Some(y) = y {
+                    agent.control.y = direction.y * agent.blackboard.scalar(y);
+                }
+            }
+        }
+        true
+    }
+}
+
+impl Node {
+    /// Runs the node and its children, true when it succeeds
+    pub fn tick(&self, agent: &mut Agent) -> bool {
+        match self {
+            Self::Sequence(children) => children.iter().all(|c| c.tick(agent)),
+            Self::Selector(children) => children.iter().any(|c| c.tick(agent)),
+            Self::Condition(condition) => condition.holds(agent),
+            Self::Action(action) => action.run(agent),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BehaviorTreeLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BehaviorTreeLoaderError {
+    #[error("Could not read the behavior tree: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the behavior tree: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for BehaviorTreeLoader {
+    type Asset = BehaviorTree;
+    type Settings = ();
+    type Error = BehaviorTreeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BehaviorTree, BehaviorTreeLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<BehaviorTree>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bt.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct BehaviorTrees(HashMap<&'static str, Handle<BehaviorTree>>);
+
+fn load_trees(mut commands: Commands, asset_server: Res<AssetServer>, mut required: ResMut<RequiredAssets>) {
+    let trees = TREES
+        .iter()
+        .map(|name| {
+            let handle: Handle<BehaviorTree> = asset_server.load(format!("behaviors/{}.bt.ron", name));
+            required.add(handle.clone());
+            (*name, handle)
+        })
+        .collect();
+    commands.insert_resource(BehaviorTrees(trees));
+}
+
+/// Every blackboard targets the nearest player
+fn find_targets(mut q: Query<(&mut Blackboard, &Position)>, q_players: Query<&Position, With<PlayerController>>) {
+    for (mut bb, pos) in q.iter_mut() {
+        bb.target = q_players
+            .iter()
+            .map(|p| p.0)
+            .min_by(|a, b| a.distance2(pos.0).total_cmp(&b.distance2(pos.0)));
+    }
+}
+
+fn run_trees(
+    mut q: Query<(Entity, &BehaviorTreeRunner, &mut Blackboard, &Position, &mut MovementControl)>,
+    handles: Res<BehaviorTrees>,
+    trees: Res<Assets<BehaviorTree>>,
+    time: Res<SimulationClock>,
+) {
+    let dt = time.delta_seconds();
+    for (ent, runner, mut bb, pos, mut mc) in q.iter_mut() {
+        let Some(tree) = handles.0.get(runner.tree).and_then(|h| trees.get(h)) else {
+            continue;
+        };
+        bb.time += dt;
+        let state = bb.state.clone();
+        let mut agent = Agent {
+            blackboard: &mut bb,
+            position: pos.0,
+            control: &mut mc.0 .0,
+        };
+        tree.root.tick(&mut agent);
+        if bb.state != state {
+            debug!(target: "joust::behavior", "{:?} {} -> {}", ent, state, bb.state);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_pter_dives_at_targets_below() {
+    let tree = ron::de::from_str::<BehaviorTree>(include_str!("../../assets/behaviors/pter.bt.ron")).unwrap();
+    let mut bb = Blackboard::new("Coasting");
+    bb.set_value("flap_force", 10.0);
+    bb.time = 3.0;
+    bb.target = Some(V2::new(0.0, -5.0));
+    let mut control = V2::new(0.0, 0.0);
+
+    let tick = |bb: &mut Blackboard, control: &mut V2| {
+        tree.root.tick(&mut Agent {
+            blackboard: bb,
+            position: V2::new(0.0, 0.0),
+            control,
+        });
+    };
+    // Flaps while the dive timer runs
+    tick(&mut bb, &mut control);
+    assert_eq!(bb.state, "Flapping");
+    assert_eq!(control.y, 10.0);
+    assert_eq!(bb.timer("flap"), 0.0);
+    tick(&mut bb, &mut control);
+    assert_eq!(bb.state, "Coasting");
+
+    bb.time = 8.0;
+    tick(&mut bb, &mut control);
+    assert_eq!(bb.state, "Diving");
+    assert_eq!(control.y, -3.0);
+    assert_eq!(bb.timer("dive"), 0.0);
+}
@@ -0,0 +1,125 @@
+/*
+Screen shake and hit-stop, for the hits that should be felt
+
+Send an ImpactEvent with an intensity from 0 to 1. It adds that much trauma
+to the camera, and freezes the simulation for a few frames, more for harder
+hits. The camera shakes by the square of its trauma, so small hits barely
+move it, and the trauma wears off over time.
+
+The shake is on wall-clock time so it plays out during the hit-stop, which
+only holds the SimulationClock still. Turning screen shake off in the
+options keeps the hit-stop.
+
+Jousts, pterodactyl kills and lava deaths send impacts.
+*/
+
+use crate::engine::simulation_clock::SimulationClockCommand;
+use crate::level::hazards::HazardEvent;
+use crate::player::player_damage::JoustEvent;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Pixels the camera moves at full trauma
+const MAX_OFFSET: f32 = 12.0;
+/// Radians the camera rolls at full trauma
+const MAX_ROLL: f32 = 0.02;
+/// Trauma lost a second
+const TRAUMA_DECAY: f32 = 1.5;
+/// Frames the simulation freezes for at full intensity
+const MAX_HIT_STOP_FRAMES: f32 = 8.0;
+
+pub struct CameraEffectsPlugin;
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImpactEvent>();
+        app.init_resource::<CameraShake>();
+        app.add_systems(
+            Update,
+            ((joust_impacts, hazard_impacts), apply_impacts, shake_camera).chain(),
+        );
+    }
+}
+
+/// A hit, from 0 for a tap to 1 for the hardest
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ImpactEvent {
+    pub intensity: f32,
+}
+
+#[derive(Resource)]
+pub struct CameraShake {
+    /// From 0 to 1
+    pub trauma: f32,
+    /// Offset and roll applied to the camera last frame
+    applied: (Vec2, f32),
+}
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            applied: (Vec2::ZERO, 0.0),
+        }
+    }
+}
+
+fn joust_impacts(mut events: EventReader<JoustEvent>, mut impacts: EventWriter<ImpactEvent>) {
+    for e in events.read() {
+        let intensity = match *e {
+            JoustEvent::Bounce { .. } => 0.25,
+            JoustEvent::EnemyUnseated { .. } => 0.4,
+            JoustEvent::PlayerUnseated { by_pter: true, .. } => 0.8,
+            JoustEvent::PlayerUnseated { .. } => 0.6,
+            JoustEvent::EggCrushed { .. } => continue,
+        };
+        impacts.send(ImpactEvent { intensity });
+    }
+}
+
+fn hazard_impacts(mut events: EventReader<HazardEvent>, mut impacts: EventWriter<ImpactEvent>) {
+    for e in events.read() {
+        if let HazardEvent::Burned { player, .. } = *e {
+            let intensity = if player.is_some() { 0.7 } else { 0.3 };
+            impacts.send(ImpactEvent { intensity });
+        }
+    }
+}
+
+fn apply_impacts(
+    mut events: EventReader<ImpactEvent>,
+    mut shake: ResMut<CameraShake>,
+    mut clock: EventWriter<SimulationClockCommand>,
+) {
+    for e in events.read() {
+        let intensity = e.intensity.clamp(0.0, 1.0);
+        shake.trauma = (shake.trauma + intensity).min(1.0);
+        let frames = (intensity * MAX_HIT_STOP_FRAMES).round() as u32;
+        if frames > 0 {
+            clock.send(SimulationClockCommand::HitStop(frames));
+        }
+    }
+}
+
+/// Moves the camera around where it would be without the shake
+fn shake_camera(
+    mut shake: ResMut<CameraShake>,
+    mut q_camera: Query<&mut Transform, With<Camera2d>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let amount = if settings.screen_shake { shake.trauma * shake.trauma } else { 0.0 };
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.0);
+    if amount == 0.0 && shake.applied == (Vec2::ZERO, 0.0) {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * MAX_OFFSET * amount;
+    let roll = rng.gen_range(-1.0..=1.0) * MAX_ROLL * amount;
+    let (last_offset, last_roll) = shake.applied;
+    for mut t in q_camera.iter_mut() {
+        t.translation += (offset - last_offset).extend(0.0);
+        t.rotate_z(roll - last_roll);
+    }
+    shake.applied = (offset, roll);
+}
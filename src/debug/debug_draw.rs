@@ -0,0 +1,130 @@
+/*
+Draws physics and collision state with gizmos
+
+Press F1 to toggle the overlay. Drawn are:
+    SquareCollider bounds, including their offsets. Colliders that are
+        wrapped around the world border are drawn as two halves
+    Velocity and force vectors
+    GroundedTo links, from the grounded entity to its static object
+    Contact points of static collisions
+*/
+
+use crate::engine::collision::{Grounded, GroundedState, SquareCollider, StaticCollisionEvent};
+use crate::engine::physics::{Force, Mass, PhysicsStages, Position, StaticObject, Velocity, V2};
+use crate::engine::wraparound::{BorderDistance, WraparoundGhost};
+use crate::engine::physics::PhysicsScale;
+use bevy::prelude::*;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F1;
+/// Velocity vectors are drawn this many times longer than one second of travel
+const VELOCITY_DRAW_SCALE: f32 = 0.5;
+/// Force vectors are drawn as the acceleration they cause, times this
+const FORCE_DRAW_SCALE: f32 = 0.05;
+
+pub struct DebugDrawPlugin;
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugDrawEnabled>();
+        app.add_systems(Update, toggle_debug_draw);
+        app.add_systems(
+            Update,
+            (
+                draw_colliders,
+                draw_motion_vectors,
+                draw_grounded_links,
+                draw_contact_points,
+            )
+                .after(PhysicsStages::CalculateNextVelocities)
+                .run_if(debug_draw_enabled),
+        );
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct DebugDrawEnabled(pub bool);
+
+fn debug_draw_enabled(enabled: Res<DebugDrawEnabled>) -> bool {
+    enabled.0
+}
+
+fn toggle_debug_draw(mut enabled: ResMut<DebugDrawEnabled>, key_in: Res<Input<KeyCode>>) {
+    if key_in.just_pressed(TOGGLE_KEY) {
+        enabled.0 = !enabled.0;
+    }
+}
+
+fn to_screen(p: V2, scale: f32) -> Vec2 {
+    Vec2::new(p.x * scale, p.y * scale)
+}
+
+fn draw_colliders(
+    mut gizmos: Gizmos,
+    q: Query<(&SquareCollider, &Position, Option<&StaticObject>), Without<WraparoundGhost>>,
+    border_distance: Res<BorderDistance>,
+    ps: Res<PhysicsScale>,
+) {
+    let half_border = border_distance.0 / 2.0;
+    for (sc, pos, maybe_static) in q.iter() {
+        let color = if maybe_static.is_some() {
+            Color::LIME_GREEN
+        } else {
+            Color::CYAN
+        };
+        let (min, max) = sc.border_adjusted_bounds(border_distance.0, pos.0);
+        if min.x > max.x {
+            // Wrapped around the world border, draws the two halves
+            draw_bounds(&mut gizmos, V2::new(min.x, min.y), V2::new(half_border, max.y), ps.0, color);
+            draw_bounds(&mut gizmos, V2::new(-half_border, min.y), V2::new(max.x, max.y), ps.0, color);
+        } else {
+            draw_bounds(&mut gizmos, min, max, ps.0, color);
+        }
+        // The position that the collider is offset from
+        gizmos.circle_2d(to_screen(pos.0, ps.0), 2.0, color);
+    }
+}
+
+fn draw_bounds(gizmos: &mut Gizmos, min: V2, max: V2, scale: f32, color: Color) {
+    let center = to_screen((min + max) / 2.0, scale);
+    let size = to_screen(max - min, scale);
+    gizmos.rect_2d(center, 0.0, size, color);
+}
+
+fn draw_motion_vectors(
+    mut gizmos: Gizmos,
+    q: Query<(&Position, &Velocity, Option<(&Force, &Mass)>), (Without<StaticObject>, Without<WraparoundGhost>)>,
+    ps: Res<PhysicsScale>,
+) {
+    for (pos, vel, maybe_force) in q.iter() {
+        let start = to_screen(pos.0, ps.0);
+        gizmos.ray_2d(start, to_screen(vel.0 * VELOCITY_DRAW_SCALE, ps.0), Color::YELLOW);
+        if let Some((force, mass)) = maybe_force {
+            let acc = force.0 / mass.0;
+            gizmos.ray_2d(start, to_screen(acc * FORCE_DRAW_SCALE, ps.0), Color::ORANGE_RED);
+        }
+    }
+}
+
+fn draw_grounded_links(
+    mut gizmos: Gizmos,
+    q: Query<(&Grounded, &Position)>,
+    q_static: Query<&Position, With<StaticObject>>,
+    ps: Res<PhysicsScale>,
+) {
+    for (grounded, pos) in q.iter() {
+        if let GroundedState::GroundedTo(ground_ent) = grounded.0 {
+            if let Ok(ground_pos) = q_static.get(ground_ent) {
+                gizmos.line_2d(to_screen(pos.0, ps.0), to_screen(ground_pos.0, ps.0), Color::FUCHSIA);
+            }
+        }
+    }
+}
+
+fn draw_contact_points(
+    mut gizmos: Gizmos,
+    mut events: EventReader<StaticCollisionEvent>,
+    ps: Res<PhysicsScale>,
+) {
+    for e in events.read() {
+        gizmos.circle_2d(to_screen(e.contact, ps.0), 3.0, Color::RED);
+    }
+}
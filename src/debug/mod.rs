@@ -0,0 +1,17 @@
+/*
+Developer tooling that is only compiled into debug builds
+*/
+
+pub mod debug_draw;
+pub mod sim_controls;
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+pub struct DebugPlugins;
+impl PluginGroup for DebugPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(debug_draw::DebugDrawPlugin)
+            .add(sim_controls::SimControlsPlugin)
+    }
+}
@@ -0,0 +1,48 @@
+/*
+Keyboard controls for the simulation clock, for frame by frame debugging
+
+    F5: pause / resume
+    F6: step one tick while paused
+    F7: halve the time scale
+    F8: double the time scale
+*/
+
+use crate::engine::simulation_clock::{SimulationClock, SimulationClockCommand};
+use bevy::prelude::*;
+
+const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f32 = 4.0;
+
+pub struct SimControlsPlugin;
+impl Plugin for SimControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, sim_control_input);
+    }
+}
+
+fn sim_control_input(
+    key_in: Res<Input<KeyCode>>,
+    clock: Res<SimulationClock>,
+    mut commands: EventWriter<SimulationClockCommand>,
+) {
+    use SimulationClockCommand::*;
+    if key_in.just_pressed(KeyCode::F5) {
+        commands.send(TogglePause);
+        debug!(target: "joust::debug", "Simulation paused: {}", !clock.is_paused());
+    }
+    if key_in.just_pressed(KeyCode::F6) {
+        commands.send(Step);
+    }
+    let mut time_scale = clock.time_scale();
+    if key_in.just_pressed(KeyCode::F7) {
+        time_scale /= 2.0;
+    }
+    if key_in.just_pressed(KeyCode::F8) {
+        time_scale *= 2.0;
+    }
+    if time_scale != clock.time_scale() {
+        let time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        commands.send(SetTimeScale(time_scale));
+        debug!(target: "joust::debug", "Simulation time scale: {}", time_scale);
+    }
+}
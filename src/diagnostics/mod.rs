@@ -0,0 +1,121 @@
+/*
+Simulation diagnostics
+
+Registers bevy diagnostics for entity counts per kind, collision pair
+counts and the time taken by one physics step. These, together with FPS,
+are shown on an overlay that is toggled with F3.
+
+Log output is routed through bevy::log with one target per subsystem:
+    joust::assets
+    joust::collision
+    joust::wraparound
+    joust::eggman
+Use RUST_LOG to see them, for example `RUST_LOG=info,joust::collision=trace`
+*/
+
+pub mod overlay;
+
+use crate::engine::collision::{CalculateCollisions, NonStaticCollisionEvent, StaticCollisionEvent};
+use crate::engine::physics::PhysicsStages;
+use crate::engine::wraparound::WraparoundGhost;
+use crate::entities::eggman::Eggman;
+use crate::entities::platform::PlatformSprite;
+use crate::entities::pter::PterSprite;
+use crate::entities::rider::RiderSprite;
+use bevy::diagnostic::{
+    Diagnostic, DiagnosticId, Diagnostics, FrameTimeDiagnosticsPlugin, RegisterDiagnostic,
+};
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+pub const RIDER_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0001);
+pub const PTER_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0002);
+pub const EGGMAN_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0003);
+pub const PLATFORM_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0004);
+pub const GHOST_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0005);
+pub const STATIC_COLLISION_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0006);
+pub const NON_STATIC_COLLISION_COUNT: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0007);
+pub const PHYSICS_STEP_TIME: DiagnosticId = DiagnosticId::from_u128(0x3c1a_5e0f_93b4_4bd6_8f2e_0d6c_1b7a_0008);
+
+const HISTORY_LENGTH: usize = 20;
+
+pub struct JoustDiagnosticsPlugin;
+impl Plugin for JoustDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((FrameTimeDiagnosticsPlugin, overlay::DiagnosticsOverlayPlugin));
+
+        for (id, name) in [
+            (RIDER_COUNT, "riders"),
+            (PTER_COUNT, "pterodactyls"),
+            (EGGMAN_COUNT, "eggmen"),
+            (PLATFORM_COUNT, "platforms"),
+            (GHOST_COUNT, "wraparound ghosts"),
+            (STATIC_COLLISION_COUNT, "static collisions"),
+            (NON_STATIC_COLLISION_COUNT, "non static collisions"),
+        ] {
+            app.register_diagnostic(Diagnostic::new(id, name, HISTORY_LENGTH));
+        }
+        app.register_diagnostic(
+            Diagnostic::new(PHYSICS_STEP_TIME, "physics step", HISTORY_LENGTH).with_suffix("ms"),
+        );
+
+        app.init_resource::<PhysicsStepStart>();
+        app.add_systems(
+            Update,
+            (
+                begin_physics_step
+                    .before(CalculateCollisions)
+                    .before(PhysicsStages::CalculateNextPositions),
+                end_physics_step.after(PhysicsStages::CalculateNextVelocities),
+                measure_entity_counts,
+                measure_collisions.after(CalculateCollisions),
+            ),
+        );
+    }
+}
+
+/// When the physics step of this frame began
+#[derive(Resource)]
+struct PhysicsStepStart(Instant);
+impl Default for PhysicsStepStart {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+fn begin_physics_step(mut start: ResMut<PhysicsStepStart>) {
+    start.0 = Instant::now();
+}
+
+fn end_physics_step(mut diagnostics: Diagnostics, start: Res<PhysicsStepStart>) {
+    diagnostics.add_measurement(PHYSICS_STEP_TIME, || {
+        start.0.elapsed().as_secs_f64() * 1000.0
+    });
+}
+
+fn measure_entity_counts(
+    mut diagnostics: Diagnostics,
+    q_rider: Query<(), With<RiderSprite>>,
+    q_pter: Query<(), With<PterSprite>>,
+    q_eggman: Query<(), With<Eggman>>,
+    q_platform: Query<(), With<PlatformSprite>>,
+    q_ghost: Query<(), With<WraparoundGhost>>,
+) {
+    diagnostics.add_measurement(RIDER_COUNT, || q_rider.iter().count() as f64);
+    diagnostics.add_measurement(PTER_COUNT, || q_pter.iter().count() as f64);
+    diagnostics.add_measurement(EGGMAN_COUNT, || q_eggman.iter().count() as f64);
+    diagnostics.add_measurement(PLATFORM_COUNT, || q_platform.iter().count() as f64);
+    diagnostics.add_measurement(GHOST_COUNT, || q_ghost.iter().count() as f64);
+}
+
+fn measure_collisions(
+    mut diagnostics: Diagnostics,
+    mut static_collisions: EventReader<StaticCollisionEvent>,
+    q_non_static: Query<(), Changed<NonStaticCollisionEvent>>,
+) {
+    let static_count = static_collisions.read().count();
+    diagnostics.add_measurement(STATIC_COLLISION_COUNT, || static_count as f64);
+    diagnostics.add_measurement(NON_STATIC_COLLISION_COUNT, || {
+        q_non_static.iter().count() as f64
+    });
+}
@@ -0,0 +1,88 @@
+/*
+Text overlay in the top left corner that shows the simulation diagnostics
+*/
+
+use super::*;
+use bevy::diagnostic::DiagnosticsStore;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+pub struct DiagnosticsOverlayPlugin;
+impl Plugin for DiagnosticsOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_overlay);
+        app.add_systems(Update, (toggle_overlay, update_overlay));
+    }
+}
+
+#[derive(Component)]
+struct DiagnosticsOverlayText;
+
+/// The rows of the overlay, in order
+const OVERLAY_ROWS: [(&str, DiagnosticId); 9] = [
+    ("fps", FrameTimeDiagnosticsPlugin::FPS),
+    ("physics step", PHYSICS_STEP_TIME),
+    ("riders", RIDER_COUNT),
+    ("pterodactyls", PTER_COUNT),
+    ("eggmen", EGGMAN_COUNT),
+    ("platforms", PLATFORM_COUNT),
+    ("ghosts", GHOST_COUNT),
+    ("static collisions", STATIC_COLLISION_COUNT),
+    ("non static collisions", NON_STATIC_COLLISION_COUNT),
+];
+
+fn spawn_overlay(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 16.0,
+        color: Color::WHITE,
+        ..Default::default()
+    };
+    let sections = OVERLAY_ROWS
+        .iter()
+        .map(|_| TextSection::new("", style.clone()))
+        .collect::<Vec<_>>();
+
+    commands.spawn((
+        TextBundle::from_sections(sections).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..Default::default()
+        }),
+        DiagnosticsOverlayText,
+    ));
+}
+
+fn toggle_overlay(
+    mut q: Query<&mut Visibility, With<DiagnosticsOverlayText>>,
+    key_in: Res<Input<KeyCode>>,
+) {
+    if key_in.just_pressed(TOGGLE_KEY) {
+        for mut vis in q.iter_mut() {
+            *vis = match *vis {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+fn update_overlay(
+    mut q: Query<(&mut Text, &ViewVisibility), With<DiagnosticsOverlayText>>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    for (mut text, vis) in q.iter_mut() {
+        if !vis.get() {
+            continue;
+        }
+        for (section, (label, id)) in text.sections.iter_mut().zip(OVERLAY_ROWS.iter()) {
+            let value = diagnostics
+                .get(*id)
+                .and_then(|d| d.smoothed().map(|v| (v, d.suffix.clone())));
+            section.value = match value {
+                Some((v, suffix)) => format!("{}: {:.1}{}\n", label, v, suffix),
+                None => format!("{}: -\n", label),
+            };
+        }
+    }
+}
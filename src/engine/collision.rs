@@ -3,8 +3,11 @@ use crate::engine::physics::{
 };
 use crate::engine::wraparound::coord_space_to_wraparound_space;
 use crate::engine::wraparound::BorderDistance;
+use crate::engine::EngineSystems;
 use bevy::prelude::*;
+use crate::engine::simulation_clock::SimulationClock;
 use cgmath::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct CollisionPlugin;
 impl Plugin for CollisionPlugin {
@@ -21,8 +24,8 @@ impl Plugin for CollisionPlugin {
                 apply_collider_functions.in_set(CalculateNextForces),
             ).chain(),
             clear_grounded,
-            debug_print_grounded
-            )
+            trace_grounded
+            ).in_set(EngineSystems)
         );
     }
 }
@@ -34,11 +37,14 @@ pub struct CalculateCollisions;
 
 #[derive(Event)]
 pub struct StaticCollisionEvent {
-    e1: Entity,
-    e2: Entity,
+    pub e1: Entity,
+    pub e2: Entity,
+    /// Where the two colliders touch, in `wraparound space`
+    pub contact: V2,
 }
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SquareCollider {
     pub min: V2,
     pub max: V2,
@@ -50,7 +56,7 @@ pub struct SquareCollider {
 impl SquareCollider {
     /// Returns adjusted (min,max) based on the border and the center point
     /// coordinates will be returned in `wraparound space`
-    fn border_adjusted_bounds(&self, border_distance: f32, center_point: V2) -> (V2, V2) {
+    pub fn border_adjusted_bounds(&self, border_distance: f32, center_point: V2) -> (V2, V2) {
         let mut min = self.min + center_point + self.offset;
         let mut max = self.max + center_point + self.offset;
         min.x = coord_space_to_wraparound_space(min.x, border_distance);
@@ -92,7 +98,8 @@ impl Default for ColliderBundle {
 }
 
 /// Indicates that this entity will have its square collider 
-/// compared with all other (non static) square colliders every frame.
+/// compared with all other (non static) square colliders every frame,
+/// except for the colliders of its own children.
 /// No more than on single intersection will ever be updated on a frame.
 /// The intersecting entity is inserted as a NonStaticCollision Component.
 #[derive(Component, Copy, Clone)]
@@ -104,15 +111,18 @@ pub struct NonStaticCollisionEvent(pub Entity);
 
 fn compute_non_static_intersections(
     mut commands: Commands,
-    q1: Query<(Entity, &SquareCollider, &Position), (Without<StaticObject>, With<ShouldCalculateNonStaticIntersectionsOn>)>,
-    q2: Query<(Entity, &SquareCollider, &Position), Without<StaticObject>, >,
+    q1: Query<(Entity, &SquareCollider, &Position, Option<&Parent>), (Without<StaticObject>, With<ShouldCalculateNonStaticIntersectionsOn>)>,
+    q2: Query<(Entity, &SquareCollider, &Position, Option<&Parent>), Without<StaticObject>, >,
     //mut event_non_static_collision: EventWriter<NonStaticCollisionEvent>,
     border_distance: Res<BorderDistance>,
 ) {
-    for (ent1, sc1, pos1) in q1.iter() {
+    for (ent1, sc1, pos1, parent1) in q1.iter() {
         let adjusted_bounds_1 = sc1.border_adjusted_bounds(border_distance.0, pos1.0);
-        for (ent2, sc2, pos2) in q2.iter() {
-            if ent1.index() == ent2.index() {
+        // The parts of one entity, a parent and its children, never intersect each other
+        let root1 = parent1.map_or(ent1, |p| p.get());
+        for (ent2, sc2, pos2, parent2) in q2.iter() {
+            let root2 = parent2.map_or(ent2, |p| p.get());
+            if ent1.index() == ent2.index() || root1 == root2 {
                 continue;
             }
             let adjusted_bounds_2 = sc2.border_adjusted_bounds(border_distance.0, pos2.0);
@@ -168,7 +178,8 @@ fn compute_static_collider_forces(
             );
 
             if sv.magnitude() > 0.0 {
-                er.send(StaticCollisionEvent { e1: ent1, e2: ent2 });
+                let contact = contact_point(pos1.0 + sc1.offset, sc2, pos2.0, border_distance.0);
+                er.send(StaticCollisionEvent { e1: ent1, e2: ent2, contact });
                 // union should point from 1 to 2
                 if (pos1.0 - pos2.0).x < 0.0 {
                     sv.x *= -1.0;
@@ -184,17 +195,15 @@ fn compute_static_collider_forces(
                 if sv.x.abs() < sv.y.abs() {
                     if sv.x * rel_v.x < 0.0 {
                         pos1.0.x += sv.x;
-                        //println!("Fixing pos.x by {}", sv.x);
                         vel1.0.x -= rel_v.x;
-                        //println!("Fixing vel.x by {}", rel_v.x);
+                        trace!(target: "joust::collision", "{:?} fixing pos.x by {} vel.x by {}", ent1, sv.x, rel_v.x);
                     }
                 } else {
                     if sv.y * rel_v.y < 0.0 {
                         // Moving towards each other
                         pos1.0.y += sv.y;
-                        //println!("Fixing pos.y by {}", sv.y);
                         vel1.0.y -= rel_v.y;
-                        //println!("Fixing vel.y by {}", rel_v.y);
+                        trace!(target: "joust::collision", "{:?} fixing pos.y by {} vel.y by {}", ent1, sv.y, rel_v.y);
                     }
                     if sv.y > 0.0 {
                         // Sets grounded
@@ -204,16 +213,21 @@ fn compute_static_collider_forces(
                         }
                     }
                 }
-
-                //                println!(
-                //                    "sv: {:?}, sc1: {:?} {:?} sc2: {:?} {:?} ps {:.2} rel_v {:?}",
-                //                    sv, sc1.min, sc1.max, sc2.min, sc2.max, ps, rel_v
-                //                );
             }
         }
     }
 }
 
+/// The point on collider 2 that is closest to `center_1`
+/// The result is in `wraparound space`
+fn contact_point(center_1: V2, sc2: &SquareCollider, pos2: V2, border_distance: f32) -> V2 {
+    let min2 = sc2.min + pos2 + sc2.offset;
+    let max2 = sc2.max + pos2 + sc2.offset;
+    let x = center_1.x.clamp(min2.x, max2.x);
+    let y = center_1.y.clamp(min2.y, max2.y);
+    V2::new(coord_space_to_wraparound_space(x, border_distance), y)
+}
+
 /// What is the shortest x component and y component that would separate the two
 /// rectangles?
 ///
@@ -299,7 +313,7 @@ fn axis_collision_wraparound(
 /// Updates the accelerations of all colliders based on their stored collider forces
 fn apply_collider_functions(
     mut q: Query<(&mut Force, &ColliderForces, &SquareCollider), With<Collideable>>,
-    t: Res<Time>,
+    t: Res<SimulationClock>,
 ) {
     let dt = t.delta_seconds();
     for (mut force, cf, sc) in q.iter_mut() {
@@ -354,14 +368,14 @@ fn clear_grounded(
     }
 }
 
-fn debug_print_grounded(q: Query<(Entity, &Grounded)>) {
+fn trace_grounded(q: Query<(Entity, &Grounded)>) {
     for (e, g) in q.iter() {
         match &g.0 {
             GroundedState::GroundedTo(e2) => {
-                println!("{:?} is grounded to {:?}", e, e2);
+                trace!(target: "joust::collision", "{:?} is grounded to {:?}", e, e2);
             }
             GroundedState::NotGrounded => {
-                println!("{:?} is not grounded", e);
+                trace!(target: "joust::collision", "{:?} is not grounded", e);
             }
         }
     }
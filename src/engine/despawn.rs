@@ -3,18 +3,20 @@
  */
 
 use bevy::prelude::*;
+use crate::engine::simulation_clock::SimulationClock;
+use crate::engine::EngineSystems;
 
 pub struct DespawnPlugin;
 impl Plugin for DespawnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update,despawn);
+        app.add_systems(Update, despawn.in_set(EngineSystems));
     }
 }
 
 #[derive(Component, Deref, DerefMut)]
 pub struct DespawnTimer(pub Timer);
 
-fn despawn(mut commands: Commands, time: Res<Time>, mut q: Query<(&mut DespawnTimer, Entity)>) {
+fn despawn(mut commands: Commands, time: Res<SimulationClock>, mut q: Query<(&mut DespawnTimer, Entity)>) {
     for (mut timer, ent) in q.iter_mut() {
         timer.tick(time.delta());
 
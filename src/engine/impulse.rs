@@ -0,0 +1,87 @@
+/*
+Instantaneous, mass aware changes in velocity
+
+An impulse J changes the velocity of a body by J / Mass. Impulses can be
+sent as an ImpulseEvent, or accumulated in an Impulse component, which is
+cleared once it is applied.
+
+Impulses are applied during PhysicsStages::ApplyImpulses, directly after
+the next velocities are calculated, so they are never lost to
+ClearNextForces and they are seen by the speed clamps.
+*/
+
+use crate::engine::physics::{Mass, PhysicsStages, StaticObject, Velocity, V2};
+use crate::engine::EngineSystems;
+use bevy::prelude::*;
+use cgmath::prelude::*;
+
+pub struct ImpulsePlugin;
+impl Plugin for ImpulsePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImpulseEvent>();
+        app.add_systems(
+            Update,
+            (apply_impulse_events, apply_impulse_components)
+                .chain()
+                .in_set(PhysicsStages::ApplyImpulses)
+                .in_set(EngineSystems),
+        );
+    }
+}
+
+/// Applies `impulse` to `entity` this frame
+#[derive(Event)]
+pub struct ImpulseEvent {
+    pub entity: Entity,
+    pub impulse: V2,
+}
+impl ImpulseEvent {
+    /// Knocks `entity` back along `direction` with an impulse of `magnitude`
+    pub fn knockback(entity: Entity, direction: V2, magnitude: f32) -> Self {
+        Self {
+            entity,
+            impulse: knockback(direction, magnitude),
+        }
+    }
+}
+
+/// Impulses added to this component are applied and then cleared
+#[derive(Component, Deref, DerefMut)]
+pub struct Impulse(pub V2);
+impl Default for Impulse {
+    fn default() -> Self {
+        Self(V2::zero())
+    }
+}
+
+/// The impulse with the length of `magnitude` pointing along `direction`
+/// A zero direction gives a zero impulse
+pub fn knockback(direction: V2, magnitude: f32) -> V2 {
+    if direction.magnitude2() == 0.0 {
+        return V2::zero();
+    }
+    direction.normalize() * magnitude
+}
+
+fn apply_impulse_events(
+    mut events: EventReader<ImpulseEvent>,
+    mut q: Query<(&mut Velocity, &Mass), Without<StaticObject>>,
+) {
+    for e in events.read() {
+        if let Ok((mut vel, mass)) = q.get_mut(e.entity) {
+            vel.0 += e.impulse / mass.0;
+        }
+    }
+}
+
+fn apply_impulse_components(
+    mut q: Query<(&mut Velocity, &Mass, &mut Impulse), Without<StaticObject>>,
+) {
+    for (mut vel, mass, mut impulse) in q.iter_mut() {
+        if impulse.0 == V2::zero() {
+            continue;
+        }
+        vel.0 += impulse.0 / mass.0;
+        impulse.0 = V2::zero();
+    }
+}
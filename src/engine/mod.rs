@@ -3,12 +3,20 @@ pub mod physics;
 pub mod wraparound;
 pub mod speed_clamps;
 pub mod despawn;
+pub mod impulse;
+pub mod simulation_clock;
+pub mod render_layer;
 
 use collision::CollisionPlugin;
 use physics::PhysicsPlugin;
 use wraparound::WraparoundPlugin;
 use bevy::{app::PluginGroupBuilder, prelude::*};
 
+/// Every Update system of the engine plugins
+/// game_state decides in which states the arena is simulated
+#[derive(SystemSet, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct EngineSystems;
+
 pub struct DefaultEnginePlugins;
 impl PluginGroup for DefaultEnginePlugins {
     fn build(self) -> PluginGroupBuilder {
@@ -18,5 +26,8 @@ impl PluginGroup for DefaultEnginePlugins {
         .add(WraparoundPlugin)
         .add(speed_clamps::SpeedClampPlugin)
         .add(despawn::DespawnPlugin)
+        .add(impulse::ImpulsePlugin)
+        .add(simulation_clock::SimulationClockPlugin)
+        .add(render_layer::RenderLayerPlugin)
     }
 }
@@ -5,6 +5,8 @@ insert a PhysicsBundle
 
 use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
 use cgmath::{Point2, Vector2};
+use crate::engine::simulation_clock::SimulationClock;
+use crate::engine::EngineSystems;
 
 pub type V2 = Vector2<f32>;
 
@@ -25,6 +27,8 @@ pub enum PhysicsStages {
     CalculateNextAccelerations,
     /// The next iteration's velocities are calculated from HalfVelocities and the next Accelerations
     CalculateNextVelocities,
+    /// Impulses are added to the next velocities, see engine::impulse
+    ApplyImpulses,
 }
 
 pub struct PhysicsPlugin;
@@ -46,7 +50,7 @@ impl Plugin for PhysicsPlugin {
                     calculate_next_accelerations.in_set(CalculateNextAccelerations),
                     calculate_next_velocities.in_set(CalculateNextVelocities),
                 ).chain()
-            )
+            ).in_set(EngineSystems)
         );
 
         app.configure_sets(Update, (
@@ -54,8 +58,9 @@ impl Plugin for PhysicsPlugin {
                 ClearNextForces,
                 CalculateNextForces,
                 CalculateNextAccelerations,
-                CalculateNextVelocities
-                ).chain()
+                CalculateNextVelocities,
+                ApplyImpulses,
+                ).chain().in_set(EngineSystems)
             );
     }
 }
@@ -124,7 +129,7 @@ pub struct Acceleration(pub V2);
 #[derive(Component, Clone, Copy, Deref, DerefMut)]
 pub struct Force(pub V2);
 
-fn calculate_next_positions(mut q: Query<(&mut Position, &mut HalfVelocity, &Velocity, &Acceleration)>, t: Res<Time>){
+fn calculate_next_positions(mut q: Query<(&mut Position, &mut HalfVelocity, &Velocity, &Acceleration)>, t: Res<SimulationClock>){
     let dt = t.delta_seconds();
     for (mut pos, mut ha, v, a) in q.iter_mut() {
         (pos.0, ha.0) = next_position_and_half_velocity(pos.0, v.0, a.0, dt);
@@ -144,21 +149,26 @@ fn gravity(mut q: Query<(&mut Force, &Mass), Without<StaticObject>>, g: Res<Grav
     }
 }
 
-fn calculate_next_accelerations(mut q: Query<(&mut Acceleration, &Force, &Mass), Without<StaticObject>>, t: Res<Time>) {
+fn calculate_next_accelerations(mut q: Query<(&mut Acceleration, &Force, &Mass), Without<StaticObject>>, t: Res<SimulationClock>) {
     let dt = t.delta_seconds();
     for (mut a,f,m) in q.iter_mut() {
         a.0 = f.0 / m.0;
     }
 }
 
-fn calculate_next_velocities(mut q: Query<(&mut Velocity, &HalfVelocity, &Acceleration)>, t: Res<Time>)  {
+fn calculate_next_velocities(mut q: Query<(&mut Velocity, &HalfVelocity, &Acceleration)>, t: Res<SimulationClock>)  {
     let dt = t.delta_seconds();
     for (mut v, vh, a) in q.iter_mut() {
         v.0 = next_velocity(vh.0, a.0, dt);
     }
 }
 
-fn position_sync(mut q: Query<(&mut Transform, &Position), With<TextureAtlasSprite>>, scale: Res<PhysicsScale>) {
+/// Children are placed by their parent's transform
+#[allow(clippy::type_complexity)]
+fn position_sync(
+    mut q: Query<(&mut Transform, &Position), (With<TextureAtlasSprite>, Without<Parent>)>,
+    scale: Res<PhysicsScale>,
+) {
     for (mut t, p) in q.iter_mut() {
         t.translation.x = p.0.x * scale.0;
         t.translation.y = p.0.y * scale.0;
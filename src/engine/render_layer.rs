@@ -0,0 +1,121 @@
+/*
+Render layers, from back to front
+
+Every sprite in the arena goes on a RenderLayer, which sets the z of its
+Transform. Each layer has LAYER_DEPTH of z to itself, so a sprite never
+draws behind a sprite of a lower layer. Within a layer, a LayerOrder puts
+a sprite in front of the ones with a lower order. Orders are rounded to
+ORDER_STEPS steps, each with its own band of z, and sprites in the same
+step are told apart by their entity within the front half of the band.
+That keeps the order stable from one frame to the next, sprites at the
+same z would swap places as they please.
+
+Children are drawn relative to their parent and keep their own small z, a
+knight is just in front of their mount.
+
+Wraparound ghosts don't have a layer of their own. They are drawn at the z
+of the entity they follow, see engine::wraparound, so a ghost is behind and
+in front of the same sprites as its parent. The HUD is UI, drawn over the
+arena, the camera sits in front of the Hud layer.
+*/
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+/// z taken by every layer
+pub const LAYER_DEPTH: f32 = 10.0;
+/// z of the camera, in front of every layer
+pub const CAMERA_Z: f32 = (RenderLayer::Hud as u8 as f32 + 1.0) * LAYER_DEPTH;
+/// z between the back and the front LayerOrder of a layer
+const ORDER_DEPTH: f32 = LAYER_DEPTH / 2.0;
+/// Orders closer than one step apart are drawn in the order of their entities
+const ORDER_STEPS: u32 = 16;
+/// z of one order step
+const ORDER_BAND: f32 = ORDER_DEPTH / ORDER_STEPS as f32;
+/// Sprites of the same order step are spread over this many steps by entity
+const ENTITY_STEPS: u32 = 1024;
+
+pub struct RenderLayerPlugin;
+impl Plugin for RenderLayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, apply_render_layers.before(TransformSystem::TransformPropagate));
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    Background,
+    Platforms,
+    Lava,
+    Eggs,
+    Riders,
+    Pterodactyls,
+    Particles,
+    Hud,
+}
+
+impl RenderLayer {
+    /// z of the back of the layer
+    pub fn z(self) -> f32 {
+        self as u8 as f32 * LAYER_DEPTH
+    }
+}
+
+/// Where a sprite goes among the sprites of its layer, from 0 at the back to 1 at the front
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct LayerOrder(pub f32);
+
+impl Default for LayerOrder {
+    fn default() -> Self {
+        Self(0.0)
+    }
+}
+
+/// z of a sprite on `layer`, below the next layer
+pub fn layer_z(layer: RenderLayer, order: LayerOrder, ent: Entity) -> f32 {
+    let step = (order.0.clamp(0.0, 1.0) * ORDER_STEPS as f32).round();
+    // Kept to half a band, the rest is left to children
+    let spread = (ent.index() % ENTITY_STEPS) as f32 / ENTITY_STEPS as f32 * ORDER_BAND / 2.0;
+    layer.z() + step * ORDER_BAND + spread
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_render_layers(
+    mut q: Query<
+        (Entity, &RenderLayer, Option<&LayerOrder>, &mut Transform),
+        (Without<Parent>, Or<(Changed<RenderLayer>, Changed<LayerOrder>)>),
+    >,
+) {
+    for (ent, layer, order, mut t) in q.iter_mut() {
+        t.translation.z = layer_z(*layer, order.copied().unwrap_or_default(), ent);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_layers_never_overlap() {
+    let back = Entity::from_raw(ENTITY_STEPS - 1);
+    let front = Entity::from_raw(0);
+    assert!(layer_z(RenderLayer::Platforms, LayerOrder(1.0), back) < layer_z(RenderLayer::Lava, LayerOrder(0.0), front));
+    assert!(layer_z(RenderLayer::Riders, LayerOrder(0.0), back) < layer_z(RenderLayer::Riders, LayerOrder(0.5), front));
+    assert!(layer_z(RenderLayer::Hud, LayerOrder(1.0), back) < CAMERA_Z);
+}
+
+#[cfg(test)]
+#[test]
+fn test_higher_orders_are_in_front() {
+    let back = Entity::from_raw(ENTITY_STEPS - 1);
+    let front = Entity::from_raw(0);
+    let step = 1.0 / ORDER_STEPS as f32;
+    for i in 0..ORDER_STEPS {
+        let order = i as f32 * step;
+        assert!(
+            layer_z(RenderLayer::Lava, LayerOrder(order), back) < layer_z(RenderLayer::Lava, LayerOrder(order + step), front)
+        );
+    }
+    // Within a step, the entity decides, and keeps deciding the same way
+    let a = layer_z(RenderLayer::Lava, LayerOrder(0.5), front);
+    let b = layer_z(RenderLayer::Lava, LayerOrder(0.51), back);
+    assert!(a < b);
+    assert_eq!(a, layer_z(RenderLayer::Lava, LayerOrder(0.5), front));
+}
@@ -0,0 +1,204 @@
+/*
+The clock that drives the simulation
+
+Everything that simulates the game world (PhysicsStages, animation timers,
+despawn timers and behavior timers) reads its delta from the
+SimulationClock resource instead of bevy's Time. This lets the simulation
+be paused, slowed down, sped up, or stepped one tick at a time, while the
+rest of the app (UI, input, rendering) keeps running.
+
+A hit-stop freezes the simulation for a number of frames, to make an impact
+land harder.
+
+The clock can be driven directly through the resource, or by sending
+SimulationClockCommand events.
+*/
+
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+use std::time::Duration;
+
+/// The duration of a single step while paused
+const STEP_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+pub struct SimulationClockPlugin;
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>();
+        app.add_event::<SimulationClockCommand>();
+        app.add_systems(
+            First,
+            (apply_clock_commands, advance_simulation_clock)
+                .chain()
+                .after(TimeSystem),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct SimulationClock {
+    paused: bool,
+    time_scale: f32,
+    pending_steps: u32,
+    /// Frames left to hold the simulation still for
+    hit_stop_frames: u32,
+    delta: Duration,
+    elapsed: Duration,
+}
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            time_scale: 1.0,
+            pending_steps: 0,
+            hit_stop_frames: 0,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+impl SimulationClock {
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 1.0 is normal speed, less than 1.0 is slow motion and
+    /// more than 1.0 is fast forward
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Advances a paused simulation by a single tick on the next frame
+    pub fn step(&mut self) {
+        self.pending_steps += 1;
+    }
+
+    /// Holds the simulation still for the next `frames` frames,
+    /// unless a longer hit-stop is already running
+    pub fn hit_stop(&mut self, frames: u32) {
+        self.hit_stop_frames = self.hit_stop_frames.max(frames);
+    }
+
+    /// How much simulation time passed this frame
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Total simulation time since startup
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    fn advance(&mut self, real_delta: Duration) {
+        self.delta = if !self.paused && self.hit_stop_frames > 0 {
+            self.hit_stop_frames -= 1;
+            Duration::ZERO
+        } else if !self.paused {
+            real_delta.mul_f32(self.time_scale)
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            STEP_DURATION
+        } else {
+            Duration::ZERO
+        };
+        self.elapsed += self.delta;
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub enum SimulationClockCommand {
+    Pause,
+    Resume,
+    TogglePause,
+    SetTimeScale(f32),
+    Step,
+    /// Frames to freeze the simulation for
+    HitStop(u32),
+}
+
+fn apply_clock_commands(
+    mut events: EventReader<SimulationClockCommand>,
+    mut clock: ResMut<SimulationClock>,
+) {
+    use SimulationClockCommand::*;
+    for e in events.read() {
+        match *e {
+            Pause => clock.pause(),
+            Resume => clock.resume(),
+            TogglePause => clock.toggle_pause(),
+            SetTimeScale(s) => clock.set_time_scale(s),
+            Step => clock.step(),
+            HitStop(frames) => clock.hit_stop(frames),
+        }
+    }
+}
+
+fn advance_simulation_clock(mut clock: ResMut<SimulationClock>, time: Res<Time>) {
+    clock.advance(time.delta());
+}
+
+#[cfg(test)]
+#[test]
+fn test_simulation_clock_pause_and_step() {
+    let frame = Duration::from_millis(10);
+    let mut clock = SimulationClock::default();
+
+    clock.set_time_scale(0.5);
+    clock.advance(frame);
+    assert_eq!(clock.delta(), Duration::from_millis(5));
+
+    clock.pause();
+    clock.advance(frame);
+    assert_eq!(clock.delta(), Duration::ZERO);
+
+    clock.step();
+    clock.advance(frame);
+    assert_eq!(clock.delta(), STEP_DURATION);
+    clock.advance(frame);
+    assert_eq!(clock.delta(), Duration::ZERO);
+
+    clock.resume();
+    clock.advance(frame);
+    assert_eq!(clock.delta(), Duration::from_millis(5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_simulation_clock_hit_stop() {
+    let frame = Duration::from_millis(10);
+    let mut clock = SimulationClock::default();
+
+    clock.hit_stop(2);
+    clock.hit_stop(1);
+    clock.advance(frame);
+    assert_eq!(clock.delta(), Duration::ZERO);
+    clock.advance(frame);
+    assert_eq!(clock.delta(), Duration::ZERO);
+    clock.advance(frame);
+    assert_eq!(clock.delta(), frame);
+}
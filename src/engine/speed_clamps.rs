@@ -4,15 +4,22 @@ Allows inserting components that clamp velocity components
 
 use bevy::prelude::*;
 use crate::engine::physics::{Velocity, PhysicsStages};
+use crate::engine::EngineSystems;
+use serde::{Deserialize, Serialize};
 
 pub struct SpeedClampPlugin;
 impl Plugin for SpeedClampPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update,clamp_velocities.after(PhysicsStages::CalculateNextVelocities));
+        app.add_systems(
+            Update,
+            clamp_velocities
+                .after(PhysicsStages::ApplyImpulses)
+                .in_set(EngineSystems),
+        );
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Copy, Clone, Serialize, Deserialize)]
 pub struct SpeedClamps {
     pub pos_x: f32,
     pub neg_x: f32,
@@ -8,7 +8,9 @@ all positions are constrained to -1/2 BorderDistance to +1/2 BorderDistance
     Also, when an entity with a texture atlas handle and a position
 gets within a certain distance to the border, a ghost sprite is spawned
 on the other side of the screen. This sprite is removed after leaving a
-certain distance from the border.
+certain distance from the border, or when the entity it follows is gone.
+The ghost is as large as the entity on screen, is hidden with it, shares
+its anchor and is drawn at its z, so it sits between the same sprites.
 */
 
 use crate::{
@@ -22,6 +24,7 @@ use modulo::Mod;
 use crate::{
     engine::collision::SquareCollider,
     engine::physics::{PhysicsScale, PhysicsStages, Position, V2},
+    engine::EngineSystems,
 };
 
 pub struct WraparoundPlugin;
@@ -32,12 +35,19 @@ impl Plugin for WraparoundPlugin {
             Update,
             modulo_position_wraparound
                 .after(PhysicsStages::CalculateNextPositions)
-                .before(PhysicsStages::CalculateNextForces),
+                .before(PhysicsStages::CalculateNextForces)
+                .in_set(EngineSystems),
+        );
+        app.add_systems(
+            Update,
+            (
+                start_wraparound,
+                stop_wraparound,
+                wraparound_ghost_position_sync,
+                wraparound_ghost_texture_sync,
+            )
+                .in_set(EngineSystems),
         );
-        app.add_systems(Update,start_wraparound);
-        app.add_systems(Update,stop_wraparound);
-        app.add_systems(Update,wraparound_ghost_position_sync);
-        app.add_systems(Update,wraparound_ghost_texture_sync);
     }
 }
 
@@ -89,7 +99,7 @@ const GHOST_WRAPAROUND_THRESH: f32 = 6.0;
 fn start_wraparound(
     mut commands: Commands,
     q: Query<
-        (Entity, &Handle<TextureAtlas>, &Position, &Transform),
+        (Entity, &Handle<TextureAtlas>, &Position, &GlobalTransform),
         (
             Without<WraparoundGhost>,
             Without<ShouldntWraparound>,
@@ -99,7 +109,7 @@ fn start_wraparound(
     bd: Res<BorderDistance>,
     ps: Res<PhysicsScale>,
 ) {
-    for (ent, tas, pos, t) in q.iter() {
+    for (ent, tas, pos, gt) in q.iter() {
         if pos.0.x < -bd.0 / 2.0 + GHOST_WRAPAROUND_THRESH || pos.0.x > bd.0 / 2.0 - GHOST_WRAPAROUND_THRESH {
             let offset: f32;
             if pos.0.x > 0.0 {
@@ -113,12 +123,9 @@ fn start_wraparound(
             let ghost_ssb = SpriteSheetBundle {
                 texture_atlas: tas.clone(),
                 transform: Transform {
-                    scale: t.scale,
-                    translation: Vec3::new(
-                        ps.0 * t.translation.x,
-                        t.translation.y,
-                        t.translation.z,
-                    ),
+                    // Children are scaled by their parents too
+                    scale: gt.compute_transform().scale,
+                    translation: Vec3::new(ghost_pos.0.x * ps.0, ghost_pos.0.y * ps.0, gt.translation().z),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -135,7 +142,7 @@ fn start_wraparound(
 
             commands.entity(ent).insert(IsWrapped);
             commands.spawn(ghost);
-            println!("Spawned new wraparound ghost.");
+            trace!(target: "joust::wraparound", "Spawned new wraparound ghost for {:?}", ent);
         }
     }
 }
@@ -167,34 +174,44 @@ fn stop_wraparound(
             if let Ok(parent_ent) = parent_ent_q.get(wp.parent) {
                 commands.entity(parent_ent).remove::<IsWrapped>();
             }
-            //println!("Despawned wraparound ghost");
+            trace!(target: "joust::wraparound", "Despawned wraparound ghost {:?}", ghost_ent);
         }
     }
 }
 
 fn wraparound_ghost_position_sync(
-    mut q: Query<(&mut Position, &WraparoundGhost)>,
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut Position, &WraparoundGhost)>,
     pos_q: Query<&Position, (Without<WraparoundGhost>)>,
 ) {
-    for (mut ghost_pos, wg) in q.iter_mut() {
+    for (ghost_ent, mut ghost_pos, wg) in q.iter_mut() {
         if let Ok(pos) = pos_q.get(wg.parent) {
             ghost_pos.0.x = pos.0.x + wg.offset;
             ghost_pos.0.y = pos.0.y;
         } else {
-            println!("Could not get parent of a wraparound ghost! ghost should be despawned");
+            commands.entity(ghost_ent).despawn();
+            trace!(target: "joust::wraparound", "Despawned wraparound ghost {:?}, its parent is gone", ghost_ent);
         }
     }
 }
 
 fn wraparound_ghost_texture_sync(
-    mut q: Query<(&WraparoundGhost, &mut TextureAtlasSprite)>,
-    q_parent: Query<&TextureAtlasSprite, (Without<WraparoundGhost>)>,
+    mut q: Query<(&WraparoundGhost, &mut TextureAtlasSprite, &mut Visibility, &mut Transform)>,
+    q_parent: Query<(&TextureAtlasSprite, &InheritedVisibility, &GlobalTransform), (Without<WraparoundGhost>)>,
 ) {
-    for (wg, mut tas_ghost) in q.iter_mut() {
-        if let Ok(tas) = q_parent.get(wg.parent) {
+    for (wg, mut tas_ghost, mut vis_ghost, mut t_ghost) in q.iter_mut() {
+        if let Ok((tas, vis, gt)) = q_parent.get(wg.parent) {
+            if t_ghost.translation.z != gt.translation().z {
+                t_ghost.translation.z = gt.translation().z;
+            }
             tas_ghost.flip_x = tas.flip_x;
             tas_ghost.flip_y = tas.flip_y;
             tas_ghost.index = tas.index;
+            tas_ghost.anchor = tas.anchor;
+            let vis = if vis.get() { Visibility::Inherited } else { Visibility::Hidden };
+            if *vis_ghost != vis {
+                *vis_ghost = vis;
+            }
         }
     }
 }
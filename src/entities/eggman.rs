@@ -2,16 +2,18 @@ use crate::constants::{GLOBAL_COLLIDER_SCALE, GLOBAL_SPRITE_SCALE};
 use crate::engine::collision::{ColliderBundle, SquareCollider};
 use crate::engine::physics::V2;
 use crate::engine::physics::{Mass, PhysicsBodyBundle, Position, Velocity};
+use crate::engine::render_layer::RenderLayer;
 use crate::animation::eggman_animation::EggmanAnimationBundle;
-use crate::entities::spritesheets::EggTextureAtlas;
+use crate::entities::spritesheets::SpriteSheetRegistry;
 use crate::behavior::eggman_control::EggmanControlBundle;
+use crate::loading::{add_held_spawn_event, LoadingState};
 use bevy::prelude::*;
 
 pub struct EggmanPlugin;
 impl Plugin for EggmanPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<EggmanSpawnEvent>();
-        app.add_systems(Update,spawn_eggman_listener);
+        add_held_spawn_event::<EggmanSpawnEvent>(app);
+        app.add_systems(Update, spawn_eggman_listener.run_if(in_state(LoadingState::Ready)));
     }
 }
 
@@ -26,6 +28,7 @@ pub struct EggmanBundle {
     coll: ColliderBundle,
     phys_b: PhysicsBodyBundle,
     ecb: EggmanControlBundle,
+    layer: RenderLayer,
 }
 
 #[derive(Event)]
@@ -44,12 +47,16 @@ impl Default for EggmanSpawnEvent {
 
 fn spawn_eggman_listener(
     mut commands: Commands,
-    mut events: EventReader<EggmanSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<EggTextureAtlas>>,
+    mut events: ResMut<Events<EggmanSpawnEvent>>,
+    registry: Res<SpriteSheetRegistry>,
 ) {
-    for e in events.read() {
+    for e in events.drain() {
+        let Some(tex) = registry.atlas("egg") else {
+            warn!(target: "joust::assets", "No sprite sheet loaded for eggmen");
+            continue;
+        };
         let ssb = SpriteSheetBundle {
-            texture_atlas: q.single().clone(),
+            texture_atlas: tex.clone(),
             transform: Transform {
                 scale: Vec3::splat(2.5 * GLOBAL_SPRITE_SCALE),
                 ..Default::default()
@@ -83,6 +90,7 @@ fn spawn_eggman_listener(
             coll,
             phys_b: pb,
             ecb: EggmanControlBundle::default(),
+            layer: RenderLayer::Eggs,
         };
 
         commands.spawn(eggmanbundle);
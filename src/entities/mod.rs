@@ -1,9 +1,13 @@
 pub mod platform;
 pub mod pter;
+pub mod pxo;
 pub mod rider;
 pub mod rider_physics;
 pub mod spritesheets;
 pub mod eggman;
+pub mod particles;
+pub mod palette;
+pub mod mount;
 
 use bevy::{app::PluginGroupBuilder, prelude::*};
 
@@ -14,8 +18,10 @@ impl PluginGroup for JoustEntitiesPlugins {
             .add(pter::PterPlugin)
             .add(eggman::EggmanPlugin)
             .add(rider::RiderPlugin)
+            .add(mount::MountPlugin)
             .add(rider_physics::RiderPhysicsPlugin)
             .add(spritesheets::JoustSpriteSheetPlugin)
             .add(platform::PlatformPlugin)
+            .add(particles::ParticlePlugin)
     }
 }
@@ -0,0 +1,179 @@
+/*
+Mounts and the knights riding them
+
+A rider is two entities. The mount flies, walks and lands on the platforms,
+it has the physics body, the controls and the animation. The knight is a
+child of the mount, with a collider of its own over the mount's back, and
+follows the mount's Position. The knight of a player looks for what it
+touches like the mount does, what either of them hits the rider hits.
+
+Both are drawn with sheets of their own, in the rider layout. The knight
+sheets are cut out of the rider sheets, see joust.sheets.ron, and the
+knight shows the frame the mount's animation is on. The knight sprite is
+anchored on the seat of its kind of mount, mirrored when the mount faces
+left, so the anchor follows the knight onto its wraparound ghost.
+
+An unseated knight is gone, and leaves an egg behind when they were an
+enemy. The mount flies off the nearest side of the arena without wrapping
+around, as in the arcade game.
+*/
+
+use crate::animation::AnimationStages;
+use crate::behavior::movement_control::MovementControl;
+use crate::behavior::BehaviorSystems;
+use crate::engine::collision::{Grounded, NonStaticCollisionEvent, ShouldCalculateNonStaticIntersectionsOn, SquareCollider};
+use crate::engine::physics::{PhysicsStages, Position, V2};
+use crate::engine::simulation_clock::SimulationClock;
+use crate::engine::wraparound::{BorderDistance, ShouldntWraparound};
+use crate::engine::EngineSystems;
+use crate::entities::rider::{RiderSprite, Team};
+use crate::entities::rider_physics::{RiderSpeedCharacteristics, SpeedOverride};
+use crate::player::player_control::{apply_movement, PlayerController};
+use crate::player::Player;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+/// In front of the mount, relative to it
+const KNIGHT_Z: f32 = 0.1;
+/// Seconds between the flaps of a riderless mount
+const RIDERLESS_FLAP_INTERVAL: f32 = 0.3;
+/// How far past the border a riderless mount is despawned
+const EXIT_MARGIN: f32 = 2.0;
+
+pub struct MountPlugin;
+impl Plugin for MountPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                follow_mounts
+                    .after(PhysicsStages::CalculateNextPositions)
+                    .in_set(EngineSystems),
+                sync_knight_sprites.after(AnimationStages::ApplyAnimationState),
+                (fly_off, leave_arena).chain().in_set(BehaviorSystems),
+            ),
+        );
+    }
+}
+
+/// The knight riding the parent mount
+#[derive(Component)]
+pub struct Knight {
+    /// See RiderKind::knight_seat
+    seat: Vec2,
+}
+
+/// A mount that lost its knight, flying off the arena
+#[derive(Component)]
+pub struct Riderless {
+    /// -1 flies off to the left, 1 to the right
+    direction: f32,
+    since_flap: f32,
+}
+
+fn knight_collider() -> SquareCollider {
+    SquareCollider {
+        min: V2::new(-0.3, 0.0),
+        max: V2::new(0.3, 0.8),
+        ..Default::default()
+    }
+}
+
+/// Seats a knight drawn with the knight sheet `atlas` on the mount
+pub fn spawn_knight(
+    commands: &mut Commands,
+    mount: Entity,
+    atlas: &Handle<TextureAtlas>,
+    position: V2,
+    seat: Vec2,
+) -> Entity {
+    let knight = commands
+        .spawn((
+            Knight { seat },
+            Position(position),
+            knight_collider(),
+            SpriteSheetBundle {
+                texture_atlas: atlas.clone(),
+                transform: Transform::from_xyz(0.0, 0.0, KNIGHT_Z),
+                ..default()
+            },
+        ))
+        .id();
+    commands.entity(mount).add_child(knight);
+    knight
+}
+
+/// Takes the knight off the mount, which stops being a rider and flies off
+pub fn unseat(commands: &mut Commands, mount: Entity, position: V2) {
+    commands.entity(mount).despawn_descendants();
+    commands
+        .entity(mount)
+        .remove::<(RiderSprite, Team, Player, PlayerController, ShouldCalculateNonStaticIntersectionsOn)>()
+        .remove::<(NonStaticCollisionEvent, SpeedOverride)>()
+        .insert((
+            Riderless {
+                direction: if position.x > 0.0 { 1.0 } else { -1.0 },
+                since_flap: 0.0,
+            },
+            RiderSpeedCharacteristics::default(),
+            ShouldntWraparound,
+        ));
+}
+
+fn follow_mounts(
+    mut q_knights: Query<(&Parent, &mut Position), With<Knight>>,
+    q_mounts: Query<&Position, Without<Knight>>,
+) {
+    for (parent, mut pos) in q_knights.iter_mut() {
+        if let Ok(mount) = q_mounts.get(parent.get()) {
+            pos.0 = mount.0;
+        }
+    }
+}
+
+fn sync_knight_sprites(
+    mut q_knights: Query<(&Knight, &Parent, &Handle<TextureAtlas>, &mut TextureAtlasSprite)>,
+    q_mounts: Query<&TextureAtlasSprite, Without<Knight>>,
+    atlases: Res<Assets<TextureAtlas>>,
+) {
+    for (knight, parent, atlas, mut sprite) in q_knights.iter_mut() {
+        let Ok(mount) = q_mounts.get(parent.get()) else {
+            continue;
+        };
+        sprite.index = mount.index;
+        sprite.flip_x = mount.flip_x;
+        // The anchor is in sprite sizes, and moves the opposite way to the sprite
+        if let Some(tile) = atlases.get(atlas).and_then(|a| a.textures.get(mount.index)) {
+            let x = if mount.flip_x { -knight.seat.x } else { knight.seat.x };
+            sprite.anchor = Anchor::Custom(-Vec2::new(x, knight.seat.y) / tile.size());
+        }
+    }
+}
+
+/// Riderless mounts flap steadily towards their side of the arena
+fn fly_off(
+    mut q: Query<(&mut Riderless, &mut MovementControl, &RiderSpeedCharacteristics, &Grounded)>,
+    time: Res<SimulationClock>,
+) {
+    for (mut riderless, mc, psc, grounded) in q.iter_mut() {
+        riderless.since_flap += time.delta_seconds();
+        let flap = riderless.since_flap >= RIDERLESS_FLAP_INTERVAL;
+        if flap {
+            riderless.since_flap = 0.0;
+        }
+        let movement = V2::new(riderless.direction, if flap { 1.0 } else { 0.0 });
+        apply_movement(&movement, mc, psc, grounded);
+    }
+}
+
+fn leave_arena(
+    mut commands: Commands,
+    q: Query<(Entity, &Position), With<Riderless>>,
+    border_distance: Res<BorderDistance>,
+) {
+    for (ent, pos) in q.iter() {
+        if pos.0.x.abs() > border_distance.0 / 2.0 + EXIT_MARGIN {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
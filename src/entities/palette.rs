@@ -0,0 +1,102 @@
+/*
+Palette swaps, kept in `assets/spritesheets/joust.palettes.ron`
+
+A palette is a list of color swaps. Every pixel close enough to the `from`
+color of a swap is moved to its `to` color, keeping its small difference
+from `from`, so the shading drawn into the art survives the swap. When a
+pixel is close to more than one swap, the closest one wins. Transparent
+pixels are never swapped.
+
+The sprite sheet manifest uses palettes to derive a sheet from another one,
+see entities::spritesheets.
+*/
+
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+pub const PALETTES_PATH: &str = "spritesheets/joust.palettes.ron";
+
+/// All palettes, by name
+#[derive(Deserialize)]
+pub struct PaletteFile {
+    pub palettes: HashMap<String, Palette>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Palette {
+    /// Largest difference in any channel for a pixel to match a swap
+    #[serde(default = "default_tolerance")]
+    pub tolerance: u8,
+    pub swaps: Vec<PaletteSwap>,
+}
+
+fn default_tolerance() -> u8 {
+    16
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct PaletteSwap {
+    /// RGB
+    pub from: (u8, u8, u8),
+    pub to: (u8, u8, u8),
+}
+
+impl Palette {
+    /// Swaps the colors of straight alpha RGBA pixels in place
+    pub fn apply(&self, pixels: &mut [u8]) {
+        for px in pixels.chunks_exact_mut(4) {
+            if px[3] == 0 {
+                continue;
+            }
+            let closest = self
+                .swaps
+                .iter()
+                .map(|swap| (swap, distance(px, swap.from)))
+                .filter(|(_, d)| *d <= self.tolerance as i32)
+                .min_by_key(|(_, d)| *d);
+            let Some((swap, _)) = closest else {
+                continue;
+            };
+            let from = [swap.from.0, swap.from.1, swap.from.2];
+            let to = [swap.to.0, swap.to.1, swap.to.2];
+            for c in 0..3 {
+                px[c] = (to[c] as i32 + px[c] as i32 - from[c] as i32).clamp(0, 255) as u8;
+            }
+        }
+    }
+}
+
+/// Largest difference in any channel
+fn distance(px: &[u8], color: (u8, u8, u8)) -> i32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).abs();
+    d(px[0], color.0).max(d(px[1], color.1)).max(d(px[2], color.2))
+}
+
+#[cfg(test)]
+#[test]
+fn test_swaps_close_colors_and_keeps_shading() {
+    let palette = Palette {
+        tolerance: 16,
+        swaps: vec![
+            PaletteSwap {
+                from: (255, 255, 0),
+                to: (0, 174, 255),
+            },
+            PaletteSwap {
+                from: (255, 240, 0),
+                to: (0, 0, 255),
+            },
+        ],
+    };
+    let mut pixels = vec![
+        255, 251, 8, 255, // shaded yellow
+        255, 242, 0, 255, // closer to the second swap
+        255, 142, 82, 255, // skin, left alone
+        255, 255, 0, 0, // transparent
+    ];
+    palette.apply(&mut pixels);
+    assert_eq!(
+        pixels,
+        vec![0, 170, 255, 255, 0, 2, 255, 255, 255, 142, 82, 255, 255, 255, 0, 0]
+    );
+}
@@ -0,0 +1,258 @@
+/*
+Particles, drawn from the spurt sprite sheet
+
+Spawn a ParticleEmitter with a Position to emit particles there. An emitter
+sends out a burst of particles when it is spawned, and then `rate`
+particles a second until its DespawnTimer runs out. Emitters without a
+timer and without a rate are despawned after their burst.
+
+Particles fly off in a cone around the emitter's direction, fall with a
+part of the gravity, and play their clip once over their lifetime. They have
+no collider and no physics body, so they never collide, and at most
+MAX_PARTICLES are alive at once.
+
+Riders kick up dust when they start to skid, unseated riders lose their
+feathers, and whatever burns in the lava splashes it.
+*/
+
+use crate::animation::rider_animation::RiderAnimationState;
+use crate::constants::GLOBAL_SPRITE_SCALE;
+use crate::engine::despawn::DespawnTimer;
+use crate::engine::physics::{Gravity, Position, V2};
+use crate::engine::render_layer::RenderLayer;
+use crate::engine::simulation_clock::SimulationClock;
+use crate::entities::spritesheets::SpriteSheetRegistry;
+use crate::level::hazards::HazardEvent;
+use crate::player::player_damage::JoustEvent;
+use bevy::prelude::*;
+use rand::Rng;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+const MAX_PARTICLES: usize = 512;
+const SPURT_SHEET: &str = "spurt";
+const SPURT_CLIP: &str = "spurt";
+
+pub struct ParticlePlugin;
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (skid_dust, unseat_feathers, lava_splashes),
+                emit_particles.run_if(resource_exists::<SpriteSheetRegistry>()),
+                move_particles,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct ParticleEmitter {
+    pub sheet: &'static str,
+    pub clip: &'static str,
+    /// Particles a second, for as long as the emitter lives
+    pub rate: f32,
+    /// Particles sent out when the emitter is spawned
+    pub burst: u32,
+    /// Radians, 0 is to the right
+    pub direction: f32,
+    /// Radians on either side of the direction
+    pub spread: f32,
+    /// Speeds are picked between these
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Part of the gravity the particles fall with
+    pub gravity_scale: f32,
+    /// Seconds each particle lives
+    pub lifetime: f32,
+    pub color: Color,
+    pub scale: f32,
+    /// Particles owed by the rate, carried between frames
+    owed: f32,
+    burst_done: bool,
+}
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            sheet: SPURT_SHEET,
+            clip: SPURT_CLIP,
+            rate: 0.0,
+            burst: 0,
+            direction: FRAC_PI_2,
+            spread: PI,
+            min_speed: 1.0,
+            max_speed: 3.0,
+            gravity_scale: 0.0,
+            lifetime: 0.5,
+            color: Color::WHITE,
+            scale: 1.0,
+            owed: 0.0,
+            burst_done: false,
+        }
+    }
+}
+impl ParticleEmitter {
+    /// Dust thrown up behind a skidding rider
+    pub fn skid_dust() -> Self {
+        Self {
+            rate: 20.0,
+            burst: 3,
+            spread: 0.8,
+            min_speed: 0.5,
+            max_speed: 2.0,
+            gravity_scale: 0.1,
+            lifetime: 0.4,
+            color: Color::rgb(0.8, 0.7, 0.5),
+            scale: 0.6,
+            ..Default::default()
+        }
+    }
+
+    pub fn feathers() -> Self {
+        Self {
+            burst: 12,
+            min_speed: 1.0,
+            max_speed: 4.0,
+            gravity_scale: 0.2,
+            lifetime: 0.8,
+            color: Color::rgb(0.95, 0.95, 0.9),
+            scale: 0.5,
+            ..Default::default()
+        }
+    }
+
+    pub fn lava_splash() -> Self {
+        Self {
+            burst: 10,
+            spread: 0.6,
+            min_speed: 4.0,
+            max_speed: 8.0,
+            gravity_scale: 1.0,
+            lifetime: 0.6,
+            color: Color::rgb(1.0, 0.5, 0.1),
+            scale: 0.7,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Particle {
+    /// World units a second
+    velocity: V2,
+    gravity_scale: f32,
+    first_frame: usize,
+    last_frame: usize,
+}
+
+/// Spawns an emitter that lives for `duration` seconds, or only bursts when that is 0
+pub fn spawn_emitter(commands: &mut Commands, position: V2, emitter: ParticleEmitter, duration: f32) {
+    let mut e = commands.spawn((emitter, Position(position)));
+    if duration > 0.0 {
+        e.insert(DespawnTimer(Timer::from_seconds(duration, TimerMode::Once)));
+    }
+}
+
+fn skid_dust(mut commands: Commands, q: Query<(Ref<RiderAnimationState>, &Position)>) {
+    for (state, pos) in q.iter() {
+        if state.is_changed() && !state.is_added() && *state == RiderAnimationState::Drifting {
+            let feet = pos.0 - V2::new(0.0, 0.5);
+            spawn_emitter(&mut commands, feet, ParticleEmitter::skid_dust(), 0.25);
+        }
+    }
+}
+
+fn unseat_feathers(mut commands: Commands, mut events: EventReader<JoustEvent>) {
+    for e in events.read() {
+        if let JoustEvent::EnemyUnseated { position, .. } | JoustEvent::PlayerUnseated { position, .. } = *e {
+            spawn_emitter(&mut commands, position, ParticleEmitter::feathers(), 0.0);
+        }
+    }
+}
+
+fn lava_splashes(mut commands: Commands, mut events: EventReader<HazardEvent>) {
+    for e in events.read() {
+        if let HazardEvent::Burned { position, .. } = *e {
+            spawn_emitter(&mut commands, position, ParticleEmitter::lava_splash(), 0.0);
+        }
+    }
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    mut q_emitters: Query<(Entity, &mut ParticleEmitter, &Position, Option<&DespawnTimer>)>,
+    q_particles: Query<(), With<Particle>>,
+    registry: Res<SpriteSheetRegistry>,
+    time: Res<SimulationClock>,
+) {
+    let mut rng = rand::thread_rng();
+    let mut alive = q_particles.iter().count();
+
+    for (ent, mut emitter, pos, timer) in q_emitters.iter_mut() {
+        let mut count = 0;
+        if !emitter.burst_done {
+            emitter.burst_done = true;
+            count += emitter.burst as usize;
+        }
+        if emitter.rate > 0.0 {
+            emitter.owed += emitter.rate * time.delta_seconds();
+            count += emitter.owed as usize;
+            emitter.owed = emitter.owed.fract();
+        } else if timer.is_none() {
+            commands.entity(ent).despawn();
+        }
+
+        let (Some(atlas), Some(clip)) = (registry.atlas(emitter.sheet), registry.clip(emitter.sheet, emitter.clip))
+        else {
+            continue;
+        };
+        count = count.min(MAX_PARTICLES.saturating_sub(alive));
+        alive += count;
+        for _ in 0..count {
+            let angle = emitter.direction + rng.gen_range(-emitter.spread..=emitter.spread);
+            let speed = rng.gen_range(emitter.min_speed..=emitter.max_speed);
+            commands.spawn((
+                Particle {
+                    velocity: V2::new(angle.cos(), angle.sin()) * speed,
+                    gravity_scale: emitter.gravity_scale,
+                    first_frame: clip.first,
+                    last_frame: clip.last,
+                },
+                Position(pos.0),
+                RenderLayer::Particles,
+                DespawnTimer(Timer::from_seconds(emitter.lifetime, TimerMode::Once)),
+                SpriteSheetBundle {
+                    texture_atlas: atlas.clone(),
+                    sprite: TextureAtlasSprite {
+                        index: clip.first,
+                        color: emitter.color,
+                        ..default()
+                    },
+                    transform: Transform {
+                        scale: Vec3::splat(emitter.scale * GLOBAL_SPRITE_SCALE),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+/// Moves particles and plays their clip over their lifetime
+fn move_particles(
+    mut q: Query<(&mut Particle, &mut Position, &DespawnTimer, &mut TextureAtlasSprite)>,
+    gravity: Res<Gravity>,
+    time: Res<SimulationClock>,
+) {
+    let dt = time.delta_seconds();
+    for (mut particle, mut pos, timer, mut sprite) in q.iter_mut() {
+        let fall = gravity.0 * particle.gravity_scale * dt;
+        particle.velocity += fall;
+        pos.0 += particle.velocity * dt;
+        let frames = particle.last_frame + 1 - particle.first_frame;
+        let frame = ((timer.percent() * frames as f32) as usize).min(frames - 1);
+        sprite.index = particle.first_frame + frame;
+    }
+}
@@ -1,16 +1,18 @@
 use crate::engine::collision::{ColliderBundle, SquareCollider};
 use crate::constants::*;
 use crate::engine::physics::{Position, StaticPhysicsBodyBundle, Velocity, V2};
+use crate::engine::render_layer::RenderLayer;
 use crate::entities::spritesheets::*;
+use crate::loading::{add_held_spawn_event, LoadingState};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct PlatformPlugin;
 
 impl Plugin for PlatformPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<MediumPlatformSpawnEvent>();
-        app.add_event::<BottomPlatformSpawnEvent>();
-        app.add_systems(Update, (medium_platform_listener, bottom_platform_listener));
+        add_held_spawn_event::<PlatformSpawnEvent>(app);
+        app.add_systems(Update, platform_listener.run_if(in_state(LoadingState::Ready)));
     }
 }
 
@@ -18,62 +20,94 @@ impl Plugin for PlatformPlugin {
 pub struct PlatformBundle {
     pub sprite: SpriteSheetBundle,
     _platform: PlatformSprite,
+    pub kind: PlatformKind,
     cb: ColliderBundle,
     spbb: StaticPhysicsBodyBundle,
+    layer: RenderLayer,
 }
 
 #[derive(Component)]
 pub struct PlatformSprite;
 
+/// Every kind of platform has its own sprite and default collider
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PlatformKind {
+    Bottom,
+    Medium,
+}
+impl PlatformKind {
+    pub fn default_collider(&self) -> SquareCollider {
+        match self {
+            PlatformKind::Bottom => SquareCollider {
+                bounce: 1.0,
+                min: V2::new(-23.9, -3.0),
+                max: V2::new(23.9, 0.0),
+                offset: V2::new(0.0, 4.0),
+            },
+            PlatformKind::Medium => SquareCollider {
+                min: V2::new(-7.5, -2.2),
+                max: V2::new(15.0, 1.8),
+                offset: V2::new(0.0, 0.0),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Name of the sprite sheet in the sprite sheet registry
+    pub fn sprite_sheet(&self) -> &'static str {
+        match self {
+            PlatformKind::Bottom => "platform_bottom",
+            PlatformKind::Medium => "platform_medium",
+        }
+    }
+
+    pub fn sprite_scale(&self) -> f32 {
+        1.5
+    }
+}
+
 pub struct PlatformSpawnEventDetails {
     pub position: V2,
     pub velocity: V2,
 }
 
 #[derive(Event)]
-pub struct MediumPlatformSpawnEvent(pub PlatformSpawnEventDetails);
-#[derive(Event)]
-pub struct BottomPlatformSpawnEvent(pub PlatformSpawnEventDetails);
-
-fn medium_platform_listener(
-    mut commands: Commands,
-    mut events: EventReader<MediumPlatformSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<platform_kinds::PlatformMedium>>,
-) {
-    for e in events.iter() {
-        let tex = q.single();
-        let scale = 1.5;
-        let coll = SquareCollider {
-            min: V2::new(-7.5, -2.2),
-            max: V2::new(15.0, 1.8),
-            offset: V2::new(0.0,0.0),
-            ..Default::default()
-        };
-        spawn_platform(&mut commands, tex, e.0.position, e.0.velocity, coll, scale);
-    }
+pub struct PlatformSpawnEvent {
+    pub kind: PlatformKind,
+    pub details: PlatformSpawnEventDetails,
+    /// Replaces the default collider of this kind of platform
+    pub collider: Option<SquareCollider>,
+    /// Replaces the default sprite scale of this kind of platform
+    pub scale: Option<f32>,
 }
-fn bottom_platform_listener(
+
+fn platform_listener(
     mut commands: Commands,
-    mut events: EventReader<BottomPlatformSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<platform_kinds::PlatformBottom>>,
+    mut events: ResMut<Events<PlatformSpawnEvent>>,
+    registry: Res<SpriteSheetRegistry>,
 ) {
-    for e in events.iter() {
-        let tex = q.single();
-        let scale = 1.5;
-        let coll = SquareCollider {
-            bounce: 1.0,
-            min: V2::new(-23.9, -3.0),
-            max: V2::new(23.9, 0.0),
-            offset: V2::new(0.0, 4.0),
-            ..Default::default()
+    for e in events.drain() {
+        let Some(tex) = registry.atlas(e.kind.sprite_sheet()) else {
+            warn!(target: "joust::assets", "No sprite sheet loaded for {:?} platforms", e.kind);
+            continue;
         };
-        spawn_platform(&mut commands, tex, e.0.position, e.0.velocity, coll, scale);
+        let coll = e.collider.unwrap_or_else(|| e.kind.default_collider());
+        spawn_platform(
+            &mut commands,
+            tex,
+            e.kind,
+            e.details.position,
+            e.details.velocity,
+            coll,
+            e.scale.unwrap_or_else(|| e.kind.sprite_scale()),
+        );
     }
 }
 
-fn spawn_platform<'a, 'b>(
+pub fn spawn_platform<'a, 'b>(
     commands: &mut Commands<'b, 'a>,
     texture_at: &Handle<TextureAtlas>,
+    kind: PlatformKind,
     pos: V2,
     vel: V2,
     coll: SquareCollider,
@@ -104,9 +138,11 @@ fn spawn_platform<'a, 'b>(
 
     let pb = PlatformBundle {
         _platform: PlatformSprite,
+        kind,
         sprite: ssb,
         cb,
         spbb,
+        layer: RenderLayer::Platforms,
     };
 
     commands.spawn(pb).id()
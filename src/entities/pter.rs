@@ -1,15 +1,17 @@
 use crate::{entities::spritesheets::*, engine::{collision::SquareCollider, physics::{Mass, Position, Velocity}}, constants::GLOBAL_SPRITE_SCALE, behavior::{movement_control::MovementControl, pter_control::PterControlBundle}};
 use crate::engine::collision::ColliderBundle;
 use crate::engine::physics::{V2, PhysicsBodyBundle};
+use crate::engine::render_layer::RenderLayer;
 use crate::animation::pter_animation::PterAnimationBundle;
 
+use crate::loading::{add_held_spawn_event, LoadingState};
 use bevy::prelude::*;
 
 pub struct PterPlugin;
 impl Plugin for PterPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<PterSpawnEvent>();
-        app.add_systems(Update, pter_spawn_event_listener);
+        add_held_spawn_event::<PterSpawnEvent>(app);
+        app.add_systems(Update, pter_spawn_event_listener.run_if(in_state(LoadingState::Ready)));
     }
 }
 
@@ -28,6 +30,7 @@ pub struct PterBundle {
     pub phys_b: PhysicsBodyBundle,
     pub pc: PterControlBundle,
     pub pab: PterAnimationBundle,
+    pub layer: RenderLayer,
 }
 
 #[derive(Component)]
@@ -35,11 +38,14 @@ pub struct PterSprite;
 
 fn pter_spawn_event_listener(
     mut commands: Commands,
-    mut events: EventReader<PterSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<PterTextureAtlas>>
+    mut events: ResMut<Events<PterSpawnEvent>>,
+    registry: Res<SpriteSheetRegistry>,
 ) {
-    for e in events.iter() {
-
+    for e in events.drain() {
+        let Some(tex) = registry.atlas("pter") else {
+            warn!(target: "joust::assets", "No sprite sheet loaded for pters");
+            continue;
+        };
         let coll = ColliderBundle {
             sq: SquareCollider {
                 min: V2::new(-2.0, -0.5),
@@ -57,7 +63,7 @@ fn pter_spawn_event_listener(
         };
 
         let ssb = SpriteSheetBundle {
-            texture_atlas: q.single().clone(),
+            texture_atlas: tex.clone(),
             transform: Transform {
                 scale: Vec3::splat(2.5 * GLOBAL_SPRITE_SCALE),
                 ..Default::default()
@@ -72,6 +78,7 @@ fn pter_spawn_event_listener(
             phys_b: pb,
             pc: PterControlBundle::default(),
             pab: PterAnimationBundle::default(),
+            layer: RenderLayer::Pterodactyls,
         };
         commands.spawn(pb);
     }
@@ -0,0 +1,287 @@
+/*
+Pixelorama project files, the .pxo files in `assets/pxo`
+
+A .pxo file is a Godot compressed file:
+    "GCPF", the compression mode, the block size and the uncompressed size
+    the compressed size of every block, then the zstd compressed blocks
+    "GCPF" again
+
+Uncompressed, it holds a line of JSON describing the project, then the raw
+RGBA pixels of every cel, frame by frame and layer by layer.
+
+The visible layers of each frame are blended into one image, and the frames
+are stacked top to bottom, like the exported sprite sheets. Pixelorama
+frames have no names, so tags are the only source of animation clips.
+*/
+
+use crate::entities::spritesheets::SheetClip;
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+use std::io::Read;
+
+const MAGIC: &[u8; 4] = b"GCPF";
+const MODE_ZSTD: u32 = 2;
+
+/// Loads a .pxo file as a TextureAtlas, with one tile per frame
+#[derive(Default)]
+pub struct PxoLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PxoError {
+    #[error("Could not read the project: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a compressed Godot file")]
+    BadMagic,
+    #[error("Unsupported compression mode {0}, only zstd is supported")]
+    UnsupportedCompression(u32),
+    #[error("The file ends early")]
+    Truncated,
+    #[error("Could not decompress the project: {0}")]
+    Zstd(String),
+    #[error("Could not parse the project: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The project has {found} bytes of pixels, expected at least {expected}")]
+    MissingPixels { expected: usize, found: usize },
+}
+
+#[derive(Deserialize)]
+struct ProjectDef {
+    size_x: u32,
+    size_y: u32,
+    layers: Vec<LayerDef>,
+    frames: Vec<FrameDef>,
+    #[serde(default)]
+    tags: Vec<TagDef>,
+    #[serde(default = "default_fps")]
+    fps: f32,
+}
+
+fn default_fps() -> f32 {
+    6.0
+}
+
+#[derive(Deserialize)]
+struct LayerDef {
+    #[serde(default = "default_visible")]
+    visible: bool,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct FrameDef {
+    cels: Vec<CelDef>,
+    /// Multiplier of the project frame time
+    #[serde(default = "default_duration")]
+    duration: f32,
+}
+
+fn default_duration() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct CelDef {
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct TagDef {
+    name: String,
+    /// First frame, counting from 1
+    from: usize,
+    /// Last frame, counting from 1, inclusive
+    to: usize,
+}
+
+/// A decoded project, with the layers of every frame blended together
+pub struct PxoProject {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels of each frame
+    pub frames: Vec<Vec<u8>>,
+    /// One clip per tag
+    pub clips: HashMap<String, SheetClip>,
+}
+impl PxoProject {
+    pub fn decode(bytes: &[u8]) -> Result<Self, PxoError> {
+        let data = decompress(bytes)?;
+        let header_end = data.iter().position(|b| *b == b'\n').unwrap_or(data.len());
+        let project: ProjectDef = serde_json::from_slice(&data[..header_end])?;
+        let pixels = data.get(header_end + 1..).unwrap_or(&[]);
+
+        let cel_size = (project.size_x * project.size_y * 4) as usize;
+        let expected = cel_size * project.layers.len() * project.frames.len();
+        if pixels.len() < expected {
+            return Err(PxoError::MissingPixels {
+                expected,
+                found: pixels.len(),
+            });
+        }
+
+        let mut cels = pixels.chunks_exact(cel_size);
+        let frames = project
+            .frames
+            .iter()
+            .map(|frame| {
+                let mut image = vec![0; cel_size];
+                for (layer, cel) in project.layers.iter().zip(frame.cels.iter()) {
+                    let cel_pixels = cels.next().unwrap_or(&[]);
+                    if layer.visible {
+                        blend_over(&mut image, cel_pixels, cel.opacity);
+                    }
+                }
+                image
+            })
+            .collect();
+
+        let clips = project
+            .tags
+            .iter()
+            .filter(|tag| tag.from >= 1 && tag.from <= tag.to)
+            .map(|tag| {
+                let duration = project
+                    .frames
+                    .get(tag.from - 1)
+                    .map(|f| f.duration)
+                    .unwrap_or(1.0);
+                let clip = SheetClip {
+                    first: tag.from - 1,
+                    last: tag.to - 1,
+                    frame_duration: duration / project.fps,
+                    looping: true,
+                };
+                (tag.name.clone(), clip)
+            })
+            .collect();
+
+        Ok(Self {
+            width: project.size_x,
+            height: project.size_y,
+            frames,
+            clips,
+        })
+    }
+
+    /// Every frame, stacked top to bottom
+    pub fn to_image(&self) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.width,
+                height: self.height * self.frames.len() as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.frames.concat(),
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32, PxoError> {
+    bytes
+        .get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(PxoError::Truncated)
+}
+
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>, PxoError> {
+    if bytes.get(0..4) != Some(MAGIC) {
+        return Err(PxoError::BadMagic);
+    }
+    let mode = read_u32(bytes, 4)?;
+    if mode != MODE_ZSTD {
+        return Err(PxoError::UnsupportedCompression(mode));
+    }
+    let block_size = read_u32(bytes, 8)? as usize;
+    let total = read_u32(bytes, 12)? as usize;
+    let blocks = if block_size == 0 { 0 } else { (total + block_size - 1) / block_size };
+
+    let mut data = Vec::with_capacity(total);
+    let mut offset = 16 + 4 * blocks;
+    for block in 0..blocks {
+        let compressed_size = read_u32(bytes, 16 + 4 * block)? as usize;
+        let compressed = bytes
+            .get(offset..offset + compressed_size)
+            .ok_or(PxoError::Truncated)?;
+        let mut decoder = ruzstd::decoding::StreamingDecoder::new(compressed)
+            .map_err(|e| PxoError::Zstd(e.to_string()))?;
+        decoder.read_to_end(&mut data)?;
+        offset += compressed_size;
+    }
+    data.truncate(total);
+    Ok(data)
+}
+
+/// Blends the cel over the image, both are straight alpha RGBA
+fn blend_over(image: &mut [u8], cel: &[u8], opacity: f32) {
+    for (dst, src) in image.chunks_exact_mut(4).zip(cel.chunks_exact(4)) {
+        let src_a = src[3] as f32 / 255.0 * opacity;
+        let dst_a = dst[3] as f32 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a <= 0.0 {
+            continue;
+        }
+        for c in 0..3 {
+            let s = src[c] as f32 / 255.0;
+            let d = dst[c] as f32 / 255.0;
+            let out = (s * src_a + d * dst_a * (1.0 - src_a)) / out_a;
+            dst[c] = (out * 255.0).round() as u8;
+        }
+        dst[3] = (out_a * 255.0).round() as u8;
+    }
+}
+
+impl AssetLoader for PxoLoader {
+    type Asset = TextureAtlas;
+    type Settings = ();
+    type Error = PxoError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<TextureAtlas, PxoError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let project = PxoProject::decode(&bytes)?;
+            let texture = load_context.add_labeled_asset("texture".to_string(), project.to_image());
+            Ok(TextureAtlas::from_grid(
+                texture,
+                Vec2::new(project.width as f32, project.height as f32),
+                1,
+                project.frames.len(),
+                None,
+                None,
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pxo"]
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_decodes_pop_like_the_exported_sheet() {
+    let project = PxoProject::decode(include_bytes!("../../assets/pxo/pop.pxo")).unwrap();
+    assert_eq!((project.width, project.height), (10, 10));
+    assert_eq!(project.frames.len(), 9);
+    assert!(project.frames.iter().all(|f| f.len() == 10 * 10 * 4));
+    // Same pixel as (4, 4) of spritesheets/pop.png
+    let at = (4 * 10 + 4) * 4;
+    assert_eq!(&project.frames[0][at..at + 4], &[47, 96, 130, 255]);
+}
@@ -1,29 +1,23 @@
 use crate::engine::collision::*;
 use crate::constants::*;
 use crate::engine::physics::*;
+use crate::engine::render_layer::RenderLayer;
 use crate::animation::rider_animation::RiderAnimationBundle;
 use crate::entities::spritesheets::*;
 use crate::behavior::movement_control::MovementControl;
 use crate::player::PlayerBundle;
-use crate::player::player_control::PlayerControllerBundle;
+use crate::entities::rider_physics::{RiderSpeedCharacteristics, SpeedOverride};
+use crate::entities::mount::spawn_knight;
 
+use crate::loading::{add_held_spawn_event, LoadingState};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct RiderPlugin;
 impl Plugin for RiderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<RedRiderSpawnEvent>();
-        app.add_event::<GreyRiderSpawnEvent>();
-        app.add_event::<YellowRiderSpawnEvent>();
-        app.add_event::<IndigoRiderSpawnEvent>();
-        app.add_event::<BlueRiderSpawnEvent>();
-        app.add_systems(Update,
-            (red_rider_listener,
-             grey_rider_listener,
-             yellow_rider_listener,
-             indigo_rider_listener,
-             blue_rider_listener
-             ));
+        add_held_spawn_event::<RiderSpawnEvent>(app);
+        app.add_systems(Update, rider_listener.run_if(in_state(LoadingState::Ready)));
     }
 }
 
@@ -32,11 +26,14 @@ impl Plugin for RiderPlugin {
 pub struct RiderBundle {
     pub sprite: SpriteSheetBundle,
     rider: RiderSprite,
+    pub kind: RiderKind,
+    pub team: Team,
     pub coll_bundle: ColliderBundle,
     pub phys_b: PhysicsBodyBundle,
     pub grounded: Grounded,
     pub rab: RiderAnimationBundle,
     mc: MovementControl,
+    pub layer: RenderLayer,
 }
 
 #[derive(Component)]
@@ -46,93 +43,150 @@ pub struct RiderSprite;
 #[derive(Component)]
 pub struct IsRespawning;
 
+/// Every kind of rider has its own sprite sheet, and sheets for its knight and its mount
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RiderKind {
+    Red,
+    Grey,
+    Yellow,
+    Indigo,
+    Blue,
+}
+impl RiderKind {
+    /// Name of the sprite sheet of the knight on their mount, in the sprite sheet registry
+    pub fn sprite_sheet(&self) -> &'static str {
+        match self {
+            RiderKind::Red => "red",
+            RiderKind::Grey => "grey",
+            RiderKind::Yellow => "yellow",
+            RiderKind::Indigo => "indigo",
+            RiderKind::Blue => "blue",
+        }
+    }
+
+    /// Name of the sprite sheet of the knight alone, see entities::mount
+    pub fn knight_sheet(&self) -> &'static str {
+        match self {
+            RiderKind::Red => "red_knight",
+            RiderKind::Grey => "grey_knight",
+            RiderKind::Yellow => "yellow_knight",
+            RiderKind::Indigo => "indigo_knight",
+            RiderKind::Blue => "blue_knight",
+        }
+    }
+
+    /// Name of the sprite sheet of the mount alone, see entities::mount
+    pub fn mount_sheet(&self) -> &'static str {
+        match self {
+            RiderKind::Yellow => "bird",
+            RiderKind::Blue => "stork",
+            RiderKind::Red | RiderKind::Grey | RiderKind::Indigo => "vulture",
+        }
+    }
+
+    /// Where the middle of the knight sheet goes on the mount sheet, in
+    /// pixels right and up from its middle, with the mount facing right
+    pub fn knight_seat(&self) -> Vec2 {
+        match self {
+            RiderKind::Yellow | RiderKind::Blue => Vec2::new(1.0, -1.0),
+            RiderKind::Red | RiderKind::Grey | RiderKind::Indigo => Vec2::new(2.0, 3.0),
+        }
+    }
+
+    /// The team a rider of this kind is on, unless the spawn event says otherwise
+    pub fn default_team(&self) -> Team {
+        match self {
+            RiderKind::Yellow | RiderKind::Blue => Team::Players,
+            RiderKind::Red | RiderKind::Grey | RiderKind::Indigo => Team::Enemies,
+        }
+    }
+}
+
+/// Riders on the same team do not fight each other
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Team {
+    Players,
+    Enemies,
+}
+
 /// Information needed to spawn a new rider of any type
 pub struct RiderSpawnEventDetails {
     pub position: V2,
     pub velocity: V2,
-    pub optional_player: Option<PlayerBundle>,
 }
 impl Default for RiderSpawnEventDetails {
     fn default() -> Self {
         Self {
             position: V2::new(0.0, 0.0),
             velocity: V2::new(0.0,0.0),
-            optional_player: None,
         }
     }
 }
 
-// Spawn events for riders
-
-#[derive(Event)]
-pub struct RedRiderSpawnEvent(pub RiderSpawnEventDetails);
-#[derive(Event)]
-pub struct GreyRiderSpawnEvent(pub RiderSpawnEventDetails);
+/// Spawns a rider. Every override left as None uses the default for the kind of rider
 #[derive(Event)]
-pub struct YellowRiderSpawnEvent(pub RiderSpawnEventDetails);
-#[derive(Event)]
-pub struct IndigoRiderSpawnEvent(pub RiderSpawnEventDetails);
-#[derive(Event)]
-pub struct BlueRiderSpawnEvent(pub RiderSpawnEventDetails);
-
-// Spawner event listeners
-
-fn red_rider_listener(
-    mut commands: Commands,
-    mut events: EventReader<RedRiderSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<rider_kinds::RedRider>>,
-) {
-    for e in events.iter() {
-        let tex = q.single();
-        spawn_rider(&mut commands, tex, &e.0);
-    }
-}
-fn grey_rider_listener(
-    mut commands: Commands,
-    mut events: EventReader<GreyRiderSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<rider_kinds::GreyRider>>,
-) {
-    for e in events.iter() {
-        let tex = q.single();
-        spawn_rider(&mut commands, tex, &e.0);
-    }
+pub struct RiderSpawnEvent {
+    pub kind: RiderKind,
+    pub details: RiderSpawnEventDetails,
+    pub mass: Option<f32>,
+    pub collider: Option<SquareCollider>,
+    /// Replaces the tuned speed characteristics, tuning changes will not affect this rider
+    pub speed: Option<RiderSpeedCharacteristics>,
+    /// Makes the rider player controlled
+    pub controller: Option<PlayerBundle>,
+    pub team: Option<Team>,
 }
-fn yellow_rider_listener(
-    mut commands: Commands,
-    mut events: EventReader<YellowRiderSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<rider_kinds::YellowRider>>,
-) {
-    for e in events.iter() {
-        let tex = q.single();
-        spawn_rider(&mut commands, tex, &e.0);
+impl RiderSpawnEvent {
+    pub fn new(kind: RiderKind, details: RiderSpawnEventDetails) -> Self {
+        Self {
+            kind,
+            details,
+            mass: None,
+            collider: None,
+            speed: None,
+            controller: None,
+            team: None,
+        }
     }
 }
-fn indigo_rider_listener(
-    mut commands: Commands,
-    mut events: EventReader<IndigoRiderSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<rider_kinds::IndigoRider>>,
-) {
-    for e in events.iter() {
-        let tex = q.single();
-        spawn_rider(&mut commands, tex, &e.0);
+
+fn default_rider_collider() -> SquareCollider {
+    // TODO figure out bounds
+    SquareCollider {
+        min: V2::new(-0.5, -0.5),
+        max: V2::new(0.5, 0.5),
+        offset: V2::new(0.0, 0.0),
+        bounce: 1.0,
     }
 }
-fn blue_rider_listener(
+
+fn rider_listener(
     mut commands: Commands,
-    mut events: EventReader<BlueRiderSpawnEvent>,
-    q: Query<&Handle<TextureAtlas>, With<rider_kinds::BlueRider>>,
+    mut events: ResMut<Events<RiderSpawnEvent>>,
+    registry: Res<SpriteSheetRegistry>,
 ) {
-    for e in events.iter() {
-        let tex = q.single();
-        spawn_rider(&mut commands, tex, &e.0);
+    for e in events.drain() {
+        let (Some(tex), Some(mount_tex)) = (registry.atlas(e.kind.knight_sheet()), registry.atlas(e.kind.mount_sheet()))
+        else {
+            warn!(target: "joust::assets", "No sprite sheet loaded for {:?} riders", e.kind);
+            continue;
+        };
+        let mount = spawn_rider(&mut commands, mount_tex, &e);
+        let knight = spawn_knight(&mut commands, mount, tex, e.details.position, e.kind.knight_seat());
+        // A player's knight hits things on their own, see player::player_damage
+        if e.controller.is_some() {
+            commands.entity(knight).insert(ShouldCalculateNonStaticIntersectionsOn);
+        }
     }
 }
 
+/// Spawns the mount, the knight is seated on it by the caller
 fn spawn_rider<'a, 'b>(
     commands: &mut Commands<'b, 'a>,
     texat_h: &Handle<TextureAtlas>,
-    spawn_event_details: &RiderSpawnEventDetails,
+    spawn_event: &RiderSpawnEvent,
 ) -> Entity {
+    let spawn_event_details = &spawn_event.details;
     let ssb = SpriteSheetBundle {
         texture_atlas: texat_h.clone(),
         transform: Transform {
@@ -145,18 +199,12 @@ fn spawn_rider<'a, 'b>(
     };
 
     let coll = ColliderBundle {
-        // TODO figure out bounds
-        sq: SquareCollider {
-            min: V2::new(-0.5, -0.5),
-            max: V2::new(0.5, 0.5),
-            offset: V2::new(0.0, 0.0),
-            bounce: 1.0,
-        },
+        sq: spawn_event.collider.unwrap_or_else(default_rider_collider),
         ..Default::default()
     };
 
     let pb = PhysicsBodyBundle {
-        m: Mass(10.0),
+        m: Mass(spawn_event.mass.unwrap_or(10.0)),
         p: Position(spawn_event_details.position),
         v: Velocity(spawn_event_details.velocity),
         hv: HalfVelocity(V2::new(0.0, 0.0)),
@@ -169,18 +217,24 @@ fn spawn_rider<'a, 'b>(
     let rb = RiderBundle {
         sprite: ssb,
         rider: RiderSprite,
+        kind: spawn_event.kind,
+        team: spawn_event.team.unwrap_or_else(|| spawn_event.kind.default_team()),
         coll_bundle: coll,
         phys_b: pb,
         grounded: Grounded(GroundedState::NotGrounded),
-        rab: RiderAnimationBundle::default(),
+        rab: RiderAnimationBundle::new(spawn_event.kind.mount_sheet()),
         mc,
+        layer: RenderLayer::Riders,
     };
 
     let id = commands.spawn(rb).id();
 
-    if let Some(pcb) = spawn_event_details.optional_player {
+    if let Some(pcb) = spawn_event.controller {
         commands.entity(id).insert(pcb);
     }
+    if let Some(speed) = spawn_event.speed {
+        commands.entity(id).insert((speed, SpeedOverride));
+    }
 
     id
 }
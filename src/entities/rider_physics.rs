@@ -3,9 +3,10 @@ use crate::behavior::movement_control::MovementControl;
 use crate::engine::physics::{Acceleration, Force, PhysicsStages, Velocity};
 use crate::animation::rider_animation::RiderAnimationState;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Determines aspects that effect the feel and speed of movement
-#[derive(Component, Copy, Clone)]
+#[derive(Component, Copy, Clone, Serialize, Deserialize)]
 pub struct RiderSpeedCharacteristics {
     /// Multiplied by the left right control axis (which is between -1 and 1)
     pub ground_acceleration: f32,
@@ -15,6 +16,8 @@ pub struct RiderSpeedCharacteristics {
     pub air_top_speed_y: f32,
     pub jump_acceleration_from_air: f32,
     pub jump_acceleration_from_ground: f32,
+    /// Force applied against the velocity while drifting
+    pub friction: f32,
 }
 impl RiderSpeedCharacteristics {
     pub fn default() -> Self {
@@ -26,14 +29,19 @@ impl RiderSpeedCharacteristics {
             air_top_speed_y: 30.0,
             jump_acceleration_from_air: 300.0,
             jump_acceleration_from_ground: 700.0,
+            friction: 40.0,
         }
     }
 }
 
+/// Riders with this keep their speed characteristics when the tuning changes
+#[derive(Component, Copy, Clone)]
+pub struct SpeedOverride;
+
 pub struct RiderPhysicsPlugin;
 impl Plugin for RiderPhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, cap_velocities.after(PhysicsStages::CalculateNextVelocities));
+        app.add_systems(Update, cap_velocities.after(PhysicsStages::ApplyImpulses));
         app.add_systems(
             Update,
             friction
@@ -68,7 +76,6 @@ fn friction(
         &RiderAnimationState,
     )>,
 ) {
-    let friction_constant = 40.0;
     for (psc, vel, mc, mut force, ras) in q.iter_mut() {
         match ras {
             RiderAnimationState::Drifting => {
@@ -79,7 +86,7 @@ fn friction(
                     } else {
                         sign = -1.0;
                     }
-                    force.0.x += -1.0 * sign * vel.0.x.abs().clamp(1.0, 10.0) * friction_constant;
+                    force.0.x += -1.0 * sign * vel.0.x.abs().clamp(1.0, 10.0) * psc.friction;
                 }
             }
             _ => {}
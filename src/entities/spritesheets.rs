@@ -1,294 +1,438 @@
+/*
+Sprite sheets are described by a manifest, `assets/spritesheets/joust.sheets.ron`
+
+Every sheet in the manifest has a name, and lists:
+    the image path, relative to the assets folder
+    the tile size and layout of the grid
+    named animation clips, with frame ranges and frame durations
+
+The path may also point at a Pixelorama project, see entities::pxo. Its
+frames make the grid, and its tags are added to the clips.
+
+A sheet may be derived from another one instead of having its own image:
+it names the `base` sheet and a `palette` from the palettes file, see
+entities::palette. It shares the image, grid and clips of the base, with
+its colors swapped when the manifest is loaded. This is how the stork is
+made from the ostrich. A derived sheet only stands in for art it matches,
+and is tested against it. A derived sheet may also `keep` only some
+rectangles of every tile, the rest of the tile is cleared. This is how the
+knights are cut out of the rider sheets, see entities::mount.
+
+Once the manifest and all its images are loaded, the SpriteSheetRegistry
+resource is inserted. Look up atlases and clips by name in the registry.
+The registry follows changes to the manifest on disk.
+*/
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+use bevy::render::texture::{CompressedImageFormats, ImageSampler, ImageType, TextureError};
+use bevy::render::render_resource::TextureFormat;
+use crate::loading::RequiredAssets;
+use crate::entities::palette::{Palette, PaletteFile, PALETTES_PATH};
+use crate::entities::pxo::{PxoError, PxoLoader, PxoProject};
+
+const MANIFEST_PATH: &str = "spritesheets/joust.sheets.ron";
 
 /// Add this plugin to load the images and spritesheets
 pub struct JoustSpriteSheetPlugin;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
-pub enum SpriteSheetPluginState {
-    Loading,
-}
-
 impl Plugin for JoustSpriteSheetPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Startup,
-            (
-                load_rider_assets,
-                load_bird_assets,
-                load_parrot_assets,
-                load_spurt_assets,
-                load_platform_assets,
-                load_pter_asset,
-                load_egg_asset,
-                load_pop_asset,
-                load_crushed_asset,
-            ).in_set(SpriteSheetPluginState::Loading)
-        );
+        app.init_asset::<SpriteSheetManifest>();
+        app.init_asset_loader::<SpriteSheetManifestLoader>();
+        app.init_asset_loader::<PxoLoader>();
+        app.add_systems(Startup, load_manifest);
+        app.add_systems(Update, update_registry);
     }
 }
 
-fn load_tex_atlas<'a, 'b, T: Component>(
-    commands: &mut Commands<'b, 'a>,
-    asset_server: &mut Res<AssetServer>,
-    assets: &mut ResMut<Assets<TextureAtlas>>,
-    tile_size: Vec2,
-    rows: usize,
-    path: &str,
-    component: T,
-) -> Entity {
-    let tex_handle = asset_server.load(&format!("spritesheets/{}", path));
-    let atlas = TextureAtlas::from_grid(tex_handle, tile_size, 1, rows, None, None);
-    let atlas_handle = assets.add(atlas);
-    println!("Loading asset {}", path);
-    commands.spawn((atlas_handle, component)).id()
+/// A loaded manifest, with an atlas for every sheet
+#[derive(Asset, TypePath)]
+pub struct SpriteSheetManifest {
+    pub sheets: HashMap<String, SpriteSheet>,
 }
 
-fn load_rider_assets(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    use rider_kinds::*;
-
-    let tile_size = Vec2::new(20.0, 20.0);
-    let rows = 7;
-
-    let id = load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        tile_size,
-        rows,
-        "blue.png",
-        BlueRider,
-    );
-    commands.entity(id).insert(RiderTextureAtlas);
-    let id = load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        tile_size,
-        rows,
-        "grey.png",
-        GreyRider,
-    );
-    commands.entity(id).insert(RiderTextureAtlas);
-    let id = load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        tile_size,
-        rows,
-        "indigo.png",
-        IndigoRider,
-    );
-    commands.entity(id).insert(RiderTextureAtlas);
-    let id = load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        tile_size,
-        rows,
-        "yellow.png",
-        YellowRider,
-    );
-    commands.entity(id).insert(RiderTextureAtlas);
-    let id = load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        tile_size,
-        rows,
-        "red.png",
-        RedRider,
-    );
-    commands.entity(id).insert(RiderTextureAtlas);
+#[derive(Clone)]
+pub struct SpriteSheet {
+    pub atlas: Handle<TextureAtlas>,
+    pub clips: HashMap<String, SheetClip>,
 }
 
-fn load_bird_assets(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(18.0, 18.0),
-        7,
-        "bird.png",
-        BirdTextureAtlas,
-    );
+/// A named run of frames in a sprite sheet
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SheetClip {
+    /// Index of the first frame
+    pub first: usize,
+    /// Index of the last frame, inclusive
+    pub last: usize,
+    /// Seconds each frame is shown for
+    pub frame_duration: f32,
+    #[serde(default)]
+    pub looping: bool,
 }
 
-fn load_parrot_assets(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(20.0, 20.0),
-        4,
-        "parrot.png",
-        ParrotTextureAtlas,
-    );
+/// A sheet as written in the manifest file
+#[derive(Clone, Deserialize)]
+struct SheetDef {
+    /// Not needed for sheets derived from a base
+    #[serde(default)]
+    path: Option<String>,
+    /// Name of the sheet this one is derived from
+    #[serde(default)]
+    base: Option<String>,
+    /// Name of the palette applied to the image
+    #[serde(default)]
+    palette: Option<String>,
+    /// The parts of every tile kept from the base, all of it when empty
+    #[serde(default)]
+    keep: Vec<TileRect>,
+    /// Not needed for Pixelorama projects
+    #[serde(default)]
+    tile_size: Option<(f32, f32)>,
+    #[serde(default = "one")]
+    columns: usize,
+    #[serde(default = "one")]
+    rows: usize,
+    #[serde(default)]
+    padding: Option<(f32, f32)>,
+    #[serde(default)]
+    offset: Option<(f32, f32)>,
+    #[serde(default)]
+    clips: HashMap<String, SheetClip>,
 }
 
-fn load_spurt_assets(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(20.0, 20.0),
-        5,
-        "spurt.png",
-        SpurtTextureAtlas,
-    );
+/// Pixels of a tile, counted from its top left corner, both corners included
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct TileRect {
+    min: (u32, u32),
+    max: (u32, u32),
+}
+impl TileRect {
+    fn contains(&self, x: u32, y: u32) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
 }
 
-fn load_platform_assets(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    use platform_kinds::*;
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(500.0, 61.0),
-        1,
-        "platform_bottom.png",
-        PlatformBottom,
-    );
+fn one() -> usize {
+    1
+}
 
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(150.0, 16.0),
-        1,
-        "platform_medium.png",
-        PlatformMedium,
-    );
+#[derive(Deserialize)]
+struct ManifestDef {
+    sheets: HashMap<String, SheetDef>,
 }
 
-fn load_pter_asset(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(30.0, 12.0),
-        3,
-        "pter.png",
-        PterTextureAtlas,
-    );
+#[derive(Default)]
+pub struct SpriteSheetManifestLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpriteSheetManifestLoaderError {
+    #[error("Could not read the sprite sheet manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the sprite sheet manifest: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Could not read a sprite sheet: {0}")]
+    ReadSheet(#[from] bevy::asset::ReadAssetBytesError),
+    #[error("Could not read a Pixelorama project: {0}")]
+    Pxo(#[from] PxoError),
+    #[error("The sprite sheet {0} needs a tile size")]
+    MissingTileSize(String),
+    #[error("The sprite sheet {0} needs a path or a base")]
+    MissingPath(String),
+    #[error("The sprite sheet {0} is derived from {1}, which is not a sheet with a path")]
+    UnknownBase(String, String),
+    #[error("The sprite sheet {0} uses the palette {1}, which is not in the palettes file")]
+    UnknownPalette(String, String),
+    #[error("Could not decode a sprite sheet image: {0}")]
+    Image(#[from] TextureError),
+    #[error("Could not recolor the sprite sheet {0}")]
+    UnsupportedImage(String),
 }
 
-fn load_egg_asset(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(18.0, 18.0),
-        7,
-        "egg.png",
-        EggTextureAtlas,
-    );
+/// Takes the image, grid and clips from the base sheet
+fn resolve_base(
+    name: &str,
+    sheet: SheetDef,
+    defs: &HashMap<String, SheetDef>,
+) -> Result<SheetDef, SpriteSheetManifestLoaderError> {
+    let Some(base_name) = sheet.base.clone() else {
+        return Ok(sheet);
+    };
+    let unknown = || SpriteSheetManifestLoaderError::UnknownBase(name.to_string(), base_name.clone());
+    let base = defs.get(&base_name).ok_or_else(unknown)?;
+    if base.path.is_none() {
+        return Err(unknown());
+    }
+    let mut clips = base.clips.clone();
+    clips.extend(sheet.clips);
+    Ok(SheetDef {
+        base: None,
+        palette: sheet.palette,
+        keep: sheet.keep,
+        clips,
+        ..base.clone()
+    })
 }
 
-fn load_pop_asset(
-    mut commands: Commands, 
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
-) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(10.0,10.0),
-        9,
-        "pop.png",
-        PopTextureAtlas,
-    );
+/// Decodes a sheet image as RGBA
+fn decode_rgba(name: &str, path: &str, bytes: &[u8]) -> Result<Image, SpriteSheetManifestLoaderError> {
+    let extension = path.rsplit('.').next().unwrap_or_default();
+    let image = Image::from_buffer(
+        bytes,
+        ImageType::Extension(extension),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+    )?;
+    image
+        .convert(TextureFormat::Rgba8UnormSrgb)
+        .ok_or_else(|| SpriteSheetManifestLoaderError::UnsupportedImage(name.to_string()))
 }
 
-fn load_crushed_asset(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut assets: ResMut<Assets<TextureAtlas>>,
+/// Decodes a PNG sheet as RGBA and swaps its colors
+fn recolor_image(name: &str, path: &str, bytes: &[u8], palette: &Palette) -> Result<Image, SpriteSheetManifestLoaderError> {
+    let mut image = decode_rgba(name, path, bytes)?;
+    palette.apply(&mut image.data);
+    Ok(image)
+}
+
+/// Clears the RGBA pixels of every tile of the grid outside of the rectangles
+fn keep_tile_rects(
+    pixels: &mut [u8],
+    width: u32,
+    tile_size: (f32, f32),
+    padding: Option<(f32, f32)>,
+    offset: Option<(f32, f32)>,
+    rects: &[TileRect],
 ) {
-    load_tex_atlas(
-        &mut commands,
-        &mut asset_server,
-        &mut assets,
-        Vec2::new(18.0,18.0),
-        5,
-        "crushed.png",
-        CrushedTextureAtlas,
-    );
+    let (tile_w, tile_h) = (tile_size.0 as u32, tile_size.1 as u32);
+    let (pad_x, pad_y) = padding.map_or((0, 0), |(x, y)| (x as u32, y as u32));
+    let (off_x, off_y) = offset.map_or((0, 0), |(x, y)| (x as u32, y as u32));
+    for (i, px) in pixels.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let in_tile = |v: u32, off: u32, step: u32| v.checked_sub(off).map(|v| v % step);
+        let kept = match (in_tile(x, off_x, tile_w + pad_x), in_tile(y, off_y, tile_h + pad_y)) {
+            (Some(tx), Some(ty)) => rects.iter().any(|r| r.contains(tx, ty)),
+            _ => false,
+        };
+        if !kept {
+            px.fill(0);
+        }
+    }
 }
 
-#[derive(Component, Copy, Clone)]
-pub struct PterTextureAtlas;
+impl AssetLoader for SpriteSheetManifestLoader {
+    type Asset = SpriteSheetManifest;
+    type Settings = ();
+    type Error = SpriteSheetManifestLoaderError;
 
-#[derive(Component, Copy, Clone)]
-pub struct SpurtTextureAtlas;
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SpriteSheetManifest, SpriteSheetManifestLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let def = ron::de::from_bytes::<ManifestDef>(&bytes)?;
 
-#[derive(Component, Copy, Clone)]
-/// Indicates that this sprite sheet is a bird sprite sheet, and follows the bird sprite sheet layout convention
-pub struct BirdTextureAtlas;
+            let palettes = if def.sheets.values().any(|s| s.palette.is_some()) {
+                let bytes = load_context.read_asset_bytes(PALETTES_PATH).await?;
+                ron::de::from_bytes::<PaletteFile>(&bytes)?.palettes
+            } else {
+                HashMap::new()
+            };
 
-#[derive(Component, Copy, Clone)]
-pub struct ParrotTextureAtlas;
+            let mut sheets = HashMap::new();
+            for (name, sheet) in def.sheets.clone() {
+                let sheet = resolve_base(&name, sheet, &def.sheets)?;
+                let Some(path) = sheet.path.clone() else {
+                    return Err(SpriteSheetManifestLoaderError::MissingPath(name));
+                };
+                let palette = match &sheet.palette {
+                    Some(p) => Some(palettes.get(p).ok_or_else(|| {
+                        SpriteSheetManifestLoaderError::UnknownPalette(name.clone(), p.clone())
+                    })?),
+                    None => None,
+                };
+                debug!(target: "joust::assets", "Loading asset {}", path);
+                if path.ends_with(".pxo") {
+                    let bytes = load_context.read_asset_bytes(&path).await?;
+                    let project = PxoProject::decode(&bytes)?;
+                    let mut clips = project.clips.clone();
+                    clips.extend(sheet.clips);
+                    let atlas = if palette.is_some() || !sheet.keep.is_empty() {
+                        let mut image = project.to_image();
+                        if let Some(palette) = palette {
+                            palette.apply(&mut image.data);
+                        }
+                        let tile_size = (project.width as f32, project.height as f32);
+                        if !sheet.keep.is_empty() {
+                            keep_tile_rects(&mut image.data, project.width, tile_size, None, None, &sheet.keep);
+                        }
+                        let texture = load_context.add_labeled_asset(format!("{}/texture", name), image);
+                        let atlas = TextureAtlas::from_grid(
+                            texture,
+                            Vec2::new(tile_size.0, tile_size.1),
+                            1,
+                            project.frames.len(),
+                            None,
+                            None,
+                        );
+                        load_context.add_labeled_asset(name.clone(), atlas)
+                    } else {
+                        // The PxoLoader builds the atlas, only the tags are needed here
+                        load_context.load(&path)
+                    };
+                    sheets.insert(name, SpriteSheet { atlas, clips });
+                    continue;
+                }
 
-#[derive(Component, Copy, Clone)]
-/// Indicates that this sprite sheet is a rider sprite sheet, and follows the rider sprite sheet layout convention
-pub struct RiderTextureAtlas;
+                let Some(tile_size) = sheet.tile_size else {
+                    return Err(SpriteSheetManifestLoaderError::MissingTileSize(name));
+                };
+                let texture = if palette.is_some() || !sheet.keep.is_empty() {
+                    let bytes = load_context.read_asset_bytes(&path).await?;
+                    let mut image = match palette {
+                        Some(palette) => recolor_image(&name, &path, &bytes, palette)?,
+                        None => decode_rgba(&name, &path, &bytes)?,
+                    };
+                    if !sheet.keep.is_empty() {
+                        let width = image.texture_descriptor.size.width;
+                        keep_tile_rects(&mut image.data, width, tile_size, sheet.padding, sheet.offset, &sheet.keep);
+                    }
+                    load_context.add_labeled_asset(format!("{}/texture", name), image)
+                } else {
+                    load_context.load(&path)
+                };
+                let atlas = TextureAtlas::from_grid(
+                    texture,
+                    Vec2::new(tile_size.0, tile_size.1),
+                    sheet.columns,
+                    sheet.rows,
+                    sheet.padding.map(|(x, y)| Vec2::new(x, y)),
+                    sheet.offset.map(|(x, y)| Vec2::new(x, y)),
+                );
+                let atlas = load_context.add_labeled_asset(name.clone(), atlas);
+                sheets.insert(
+                    name,
+                    SpriteSheet {
+                        atlas,
+                        clips: sheet.clips,
+                    },
+                );
+            }
+            Ok(SpriteSheetManifest { sheets })
+        })
+    }
 
-#[derive(Component, Copy, Clone)]
-pub struct EggTextureAtlas;
+    fn extensions(&self) -> &[&str] {
+        &["sheets.ron"]
+    }
+}
 
-#[derive(Component, Copy, Clone)]
-pub struct PopTextureAtlas;
+#[derive(Resource)]
+struct SpriteSheetManifestHandle(Handle<SpriteSheetManifest>);
 
-/// Crushed Egg texture atlas
-#[derive(Component, Copy, Clone)]
-pub struct CrushedTextureAtlas;
+/// Every loaded sprite sheet by name. Inserted once the manifest and its images are loaded
+#[derive(Resource)]
+pub struct SpriteSheetRegistry {
+    sheets: HashMap<String, SpriteSheet>,
+}
+impl SpriteSheetRegistry {
+    pub fn atlas(&self, sheet: &str) -> Option<&Handle<TextureAtlas>> {
+        self.sheets.get(sheet).map(|s| &s.atlas)
+    }
 
-pub mod platform_kinds {
-    use super::*;
-    #[derive(Component, Clone, Copy)]
-    pub struct PlatformMedium;
-    #[derive(Component, Clone, Copy)]
-    pub struct PlatformBottom;
+    pub fn clip(&self, sheet: &str, clip: &str) -> Option<&SheetClip> {
+        self.sheets.get(sheet).and_then(|s| s.clips.get(clip))
+    }
+}
+
+fn load_manifest(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut required: ResMut<RequiredAssets>,
+) {
+    let handle: Handle<SpriteSheetManifest> = asset_server.load(MANIFEST_PATH);
+    required.add(handle.clone());
+    commands.insert_resource(SpriteSheetManifestHandle(handle));
 }
 
-pub mod rider_kinds {
-    use super::*;
-    #[derive(Component, Clone, Copy)]
-    pub struct RedRider;
-    #[derive(Component, Clone, Copy)]
-    pub struct GreyRider;
-    #[derive(Component, Clone, Copy)]
-    pub struct YellowRider;
-    #[derive(Component, Clone, Copy)]
-    pub struct IndigoRider;
-    #[derive(Component, Clone, Copy)]
-    pub struct BlueRider;
+fn update_registry(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SpriteSheetManifest>>,
+    handle: Res<SpriteSheetManifestHandle>,
+    manifests: Res<Assets<SpriteSheetManifest>>,
+    registry: Option<ResMut<SpriteSheetRegistry>>,
+) {
+    let mut changed = false;
+    for e in events.read() {
+        match e {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.0.id() =>
+            {
+                changed = true;
+            }
+            _ => {}
+        }
+    }
+    if !changed {
+        return;
+    }
+    let Some(manifest) = manifests.get(&handle.0) else {
+        return;
+    };
+    info!(target: "joust::assets", "Loaded {} sprite sheets", manifest.sheets.len());
+    match registry {
+        Some(mut registry) => registry.sheets = manifest.sheets.clone(),
+        None => commands.insert_resource(SpriteSheetRegistry {
+            sheets: manifest.sheets.clone(),
+        }),
+    }
+}
+
+/// The stork is only drawn walking, on a larger grid, so the derived sheet
+/// is compared with the colors of the drawn one rather than pixel by pixel
+#[cfg(test)]
+#[test]
+fn test_stork_matches_the_drawn_stork() {
+    let palettes = ron::de::from_str::<PaletteFile>(include_str!("../../assets/spritesheets/joust.palettes.ron")).unwrap();
+    let palette = &palettes.palettes["stork"];
+    let bird_bytes = include_bytes!("../../assets/spritesheets/bird.png");
+    let bird = decode_rgba("bird", "bird.png", bird_bytes).unwrap();
+    let stork = recolor_image("stork", "bird.png", bird_bytes, palette).unwrap();
+    let drawn = decode_rgba("parrot", "parrot.png", include_bytes!("../../assets/spritesheets/parrot.png")).unwrap();
+    let drawn_colors: Vec<&[u8]> = drawn.data.chunks_exact(4).filter(|px| px[3] != 0).collect();
+
+    for (before, after) in bird.data.chunks_exact(4).zip(stork.data.chunks_exact(4)) {
+        // Same silhouette as the ostrich
+        assert_eq!(before[3], after[3]);
+        if before[3] == 0 {
+            continue;
+        }
+        // None of the ostrich's blue is left
+        assert!(after[2] as i32 <= after[0] as i32 + 60, "blue left at {:?}", after);
+        if before != after {
+            let close = |c: &&[u8]| (0..3).all(|i| (c[i] as i32 - after[i] as i32).abs() <= palette.tolerance as i32);
+            assert!(drawn_colors.iter().any(close), "{:?} is not a color of the stork", after);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_keep_tile_rects() {
+    // Two 2x2 tiles side by side, one pixel of padding between them
+    let mut pixels = vec![255; 5 * 2 * 4];
+    let rects = [TileRect { min: (1, 0), max: (1, 1) }];
+    keep_tile_rects(&mut pixels, 5, (2.0, 2.0), Some((1.0, 0.0)), None, &rects);
+    let kept = pixels.chunks_exact(4).map(|px| px[3] != 0).collect::<Vec<_>>();
+    assert_eq!(
+        kept,
+        vec![false, true, false, false, true, false, true, false, false, true]
+    );
 }
@@ -0,0 +1,437 @@
+/*
+The flow of the game, from the title screen to game over
+
+    Title: waits for Enter once the assets are loaded, shows the attract
+        mode after a while
+    Options: the options menu, opened from the title or pause screen
+    Attract: a demo rider plays the first waves, Enter goes to Join
+    Join: players flap to join, Enter starts the game
+    Playing: the arena is built and the waves start, Escape or P pauses
+    Paused: the simulation clock is stopped, Q quits to the title
+    EnterInitials: players with a high score enter their initials,
+        see high_scores
+    GameOver: every player is out of lives, Enter continues with a fresh
+        set of lives, otherwise it is back to the title screen
+
+Every state despawns its screen when it is left. Entering the title or
+join screen despawns the arena and everything in it.
+
+Systems of the engine, behavior, hazard and player plugins are in the
+EngineSystems, BehaviorSystems, HazardSystems and PlayerSystems sets, which
+only run in the states where the arena is in use.
+*/
+
+use crate::behavior::BehaviorSystems;
+use crate::engine::physics::Position;
+use crate::engine::simulation_clock::SimulationClockCommand;
+use crate::engine::EngineSystems;
+use crate::entities::eggman::EggmanSpawnEvent;
+use crate::entities::platform::PlatformSpawnEvent;
+use crate::entities::pter::PterSpawnEvent;
+use crate::entities::rider::{RiderKind, RiderSpawnEvent};
+use crate::high_scores::{any_high_score, HighScoreTable};
+use crate::level::waves::{WaveList, WaveSystems};
+use crate::level::hazards::HazardSystems;
+use crate::level::{BuildLevelEvent, HazardZone};
+use crate::loading::LoadingState;
+use crate::player::input::InputBindings;
+use crate::player::player_control::InputSource;
+use crate::player::score::Scoreboard;
+use crate::player::PlayerSystems;
+use crate::settings::options_menu::{open_options, OptionsMenu};
+use crate::settings::Settings;
+use bevy::prelude::*;
+
+/// Seconds on the title screen before the attract mode starts
+const TITLE_IDLE_TIME: f32 = 15.0;
+/// Seconds the attract mode lasts
+const ATTRACT_TIME: f32 = 30.0;
+/// Seconds to decide whether to continue after a game over
+const CONTINUE_TIME: f32 = 10.0;
+
+const TITLE_PROMPT: &str = "PRESS ENTER";
+
+/// The controls and mount of each player that can join
+const JOIN_SLOTS: [(InputSource, RiderKind); 2] = [
+    (InputSource::Wasd, RiderKind::Yellow),
+    (InputSource::Arrows, RiderKind::Blue),
+];
+
+pub struct GameStatePlugin;
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        use GameState::*;
+        app.add_state::<GameState>();
+        app.init_resource::<TimeInState>();
+
+        app.configure_sets(
+            Update,
+            (
+                EngineSystems.run_if(arena_running),
+                BehaviorSystems.run_if(arena_running),
+                HazardSystems.run_if(arena_running),
+                PlayerSystems.run_if(players_riding),
+                WaveSystems.run_if(players_riding),
+            ),
+        );
+
+        for state in [Title, Options, Attract, Join, Playing, Paused, EnterInitials, GameOver] {
+            app.add_systems(OnEnter(state), reset_time_in_state);
+            app.add_systems(OnExit(state), despawn_screen);
+        }
+        app.add_systems(Update, tick_time_in_state);
+
+        app.add_systems(OnEnter(Title), (despawn_arena, spawn_title_screen));
+        app.add_systems(
+            Update,
+            (update_title_prompt, title_input)
+                .run_if(in_state(Title))
+                .run_if(in_state(LoadingState::Ready)),
+        );
+        app.add_systems(OnEnter(LoadingState::Failed), despawn_screen);
+
+        app.add_systems(OnEnter(Attract), (start_attract, spawn_attract_screen));
+        app.add_systems(Update, attract_input.run_if(in_state(Attract)));
+
+        app.add_systems(OnEnter(Join), (despawn_arena, reset_scoreboard, spawn_join_screen).chain());
+        app.add_systems(Update, (join_input, update_join_list).chain().run_if(in_state(Join)));
+        app.add_systems(OnTransition { from: Join, to: Playing }, build_arena);
+
+        app.add_systems(Update, (check_game_over, pause_input).run_if(in_state(Playing)));
+
+        app.add_systems(OnEnter(Paused), (pause_simulation, spawn_pause_screen));
+        // Not when the options menu is opened from the pause screen
+        app.add_systems(OnTransition { from: Paused, to: Playing }, resume_simulation);
+        app.add_systems(OnTransition { from: Paused, to: Title }, resume_simulation);
+        app.add_systems(Update, paused_input.run_if(in_state(Paused)));
+
+        app.add_systems(OnEnter(GameOver), spawn_game_over_screen);
+        app.add_systems(
+            Update,
+            (update_continue_countdown, game_over_input).run_if(in_state(GameOver)),
+        );
+    }
+}
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Title,
+    /// The options menu, see settings::options_menu
+    Options,
+    /// A demo of the game, shown while nobody is playing
+    Attract,
+    /// Players choose whether to play
+    Join,
+    Playing,
+    Paused,
+    /// The game ended with a score for the high score table
+    EnterInitials,
+    /// Every player ran out of lives
+    GameOver,
+}
+
+/// The arena is built and simulated
+pub fn arena_running(state: Res<State<GameState>>) -> bool {
+    matches!(
+        state.get(),
+        GameState::Attract | GameState::Playing | GameState::EnterInitials | GameState::GameOver
+    )
+}
+
+/// Riders are controlled by players, or by the demo
+pub fn players_riding(state: Res<State<GameState>>) -> bool {
+    matches!(state.get(), GameState::Attract | GameState::Playing)
+}
+
+/// Real seconds since the current state was entered
+#[derive(Resource, Default)]
+struct TimeInState(f32);
+
+fn reset_time_in_state(mut t: ResMut<TimeInState>) {
+    t.0 = 0.0;
+}
+
+fn tick_time_in_state(mut t: ResMut<TimeInState>, time: Res<Time>) {
+    t.0 += time.delta_seconds();
+}
+
+/// The UI of the current state, despawned when the state is left
+#[derive(Component)]
+pub(crate) struct Screen;
+
+#[derive(Component)]
+struct TitlePrompt;
+
+#[derive(Component)]
+struct JoinList;
+
+#[derive(Component)]
+struct ContinueCountdown;
+
+fn despawn_screen(mut commands: Commands, q: Query<Entity, With<Screen>>) {
+    for ent in q.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+/// A column of centered text over the whole window
+pub(crate) fn spawn_screen(commands: &mut Commands, background: Color) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(background),
+                ..default()
+            },
+            Screen,
+        ))
+        .id()
+}
+
+pub(crate) fn text(value: impl Into<String>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+}
+
+/// Despawns everything in the arena and drops spawns that are still waiting
+#[allow(clippy::type_complexity)]
+fn despawn_arena(
+    mut commands: Commands,
+    q: Query<Entity, (Or<(With<Position>, With<HazardZone>)>, Without<Parent>)>,
+    mut riders: ResMut<Events<RiderSpawnEvent>>,
+    mut pters: ResMut<Events<PterSpawnEvent>>,
+    mut eggmen: ResMut<Events<EggmanSpawnEvent>>,
+    mut platforms: ResMut<Events<PlatformSpawnEvent>>,
+    mut waves: ResMut<WaveList>,
+) {
+    for ent in q.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    riders.clear();
+    pters.clear();
+    eggmen.clear();
+    platforms.clear();
+    waves.0.clear();
+}
+
+fn build_arena(mut events: EventWriter<BuildLevelEvent>) {
+    events.send(BuildLevelEvent);
+}
+
+fn spawn_title_screen(mut commands: Commands) {
+    let screen = spawn_screen(&mut commands, Color::NONE);
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(text("JOUST", 96.0));
+        parent.spawn((text("LOADING...", 32.0), TitlePrompt));
+        parent.spawn(text("O FOR OPTIONS", 24.0));
+    });
+}
+
+/// Replaces the loading message once the assets are ready
+fn update_title_prompt(mut q: Query<&mut Text, With<TitlePrompt>>) {
+    for mut t in q.iter_mut() {
+        if t.sections[0].value != TITLE_PROMPT {
+            t.sections[0].value = TITLE_PROMPT.to_string();
+        }
+    }
+}
+
+fn title_input(
+    key_in: Res<Input<KeyCode>>,
+    time_in_state: Res<TimeInState>,
+    mut options: ResMut<OptionsMenu>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if key_in.just_pressed(KeyCode::Return) {
+        next_state.set(GameState::Join);
+    } else if key_in.just_pressed(KeyCode::O) {
+        open_options(GameState::Title, &mut options, &mut next_state);
+    } else if time_in_state.0 > TITLE_IDLE_TIME {
+        next_state.set(GameState::Attract);
+    }
+}
+
+fn start_attract(mut scoreboard: ResMut<Scoreboard>, mut events: EventWriter<BuildLevelEvent>) {
+    *scoreboard = Scoreboard::default();
+    scoreboard.join(InputSource::Demo, RiderKind::Yellow);
+    events.send(BuildLevelEvent);
+}
+
+fn spawn_attract_screen(mut commands: Commands) {
+    commands.spawn((
+        text("DEMO - PRESS ENTER", 32.0).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        }),
+        Screen,
+    ));
+}
+
+fn attract_input(
+    key_in: Res<Input<KeyCode>>,
+    time_in_state: Res<TimeInState>,
+    scoreboard: Res<Scoreboard>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if key_in.just_pressed(KeyCode::Return) {
+        next_state.set(GameState::Join);
+    } else if key_in.just_pressed(KeyCode::Escape)
+        || time_in_state.0 > ATTRACT_TIME
+        || scoreboard.is_game_over()
+    {
+        next_state.set(GameState::Title);
+    }
+}
+
+fn reset_scoreboard(mut scoreboard: ResMut<Scoreboard>, settings: Res<Settings>) {
+    *scoreboard = Scoreboard::new(settings.difficulty.starting_lives());
+}
+
+fn spawn_join_screen(mut commands: Commands) {
+    let screen = spawn_screen(&mut commands, Color::NONE);
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(text("FLAP TO JOIN", 32.0));
+        parent.spawn((text("", 32.0), JoinList));
+        parent.spawn(text("PRESS ENTER TO START", 32.0));
+    });
+}
+
+fn join_input(
+    key_in: Res<Input<KeyCode>>,
+    bindings: Res<InputBindings>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (source, kind) in JOIN_SLOTS {
+        if bindings.actions(source, &key_in).flap && !scoreboard.has_joined(source) {
+            scoreboard.join(source, kind);
+        }
+    }
+
+    if key_in.just_pressed(KeyCode::Return) {
+        // Enter on its own starts a single player game
+        if scoreboard.players.is_empty() {
+            scoreboard.join(InputSource::Wasd, RiderKind::Yellow);
+        }
+        next_state.set(GameState::Playing);
+    } else if key_in.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Title);
+    }
+}
+
+fn update_join_list(scoreboard: Res<Scoreboard>, mut q: Query<&mut Text, With<JoinList>>) {
+    if !scoreboard.is_changed() {
+        return;
+    }
+    let joined = match scoreboard.players.len() {
+        0 => String::new(),
+        1 => "1 PLAYER READY".to_string(),
+        n => format!("{} PLAYERS READY", n),
+    };
+    for mut t in q.iter_mut() {
+        t.sections[0].value = joined.clone();
+    }
+}
+
+fn check_game_over(
+    scoreboard: Res<Scoreboard>,
+    high_scores: Res<HighScoreTable>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !scoreboard.is_game_over() {
+        return;
+    }
+    if any_high_score(&scoreboard, &high_scores) {
+        next_state.set(GameState::EnterInitials);
+    } else {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn pause_input(key_in: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if key_in.any_just_pressed([KeyCode::Escape, KeyCode::P]) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn pause_simulation(mut events: EventWriter<SimulationClockCommand>) {
+    events.send(SimulationClockCommand::Pause);
+}
+
+fn resume_simulation(mut events: EventWriter<SimulationClockCommand>) {
+    events.send(SimulationClockCommand::Resume);
+}
+
+fn spawn_pause_screen(mut commands: Commands) {
+    let screen = spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.5));
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(text("PAUSED", 64.0));
+        parent.spawn(text("ESCAPE TO RESUME, O FOR OPTIONS, Q TO QUIT", 32.0));
+    });
+}
+
+fn paused_input(
+    key_in: Res<Input<KeyCode>>,
+    mut options: ResMut<OptionsMenu>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if key_in.any_just_pressed([KeyCode::Escape, KeyCode::P]) {
+        next_state.set(GameState::Playing);
+    } else if key_in.just_pressed(KeyCode::O) {
+        open_options(GameState::Paused, &mut options, &mut next_state);
+    } else if key_in.just_pressed(KeyCode::Q) {
+        next_state.set(GameState::Title);
+    }
+}
+
+fn spawn_game_over_screen(mut commands: Commands, scoreboard: Res<Scoreboard>) {
+    let screen = spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.5));
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(text("GAME OVER", 64.0));
+        for (i, p) in scoreboard.players.iter().enumerate() {
+            parent.spawn(text(format!("PLAYER {}: {}", i + 1, p.score), 32.0));
+        }
+        parent.spawn((text("", 32.0), ContinueCountdown));
+    });
+}
+
+fn update_continue_countdown(
+    time_in_state: Res<TimeInState>,
+    mut q: Query<&mut Text, With<ContinueCountdown>>,
+) {
+    let left = (CONTINUE_TIME - time_in_state.0).ceil().max(0.0);
+    for mut t in q.iter_mut() {
+        t.sections[0].value = format!("CONTINUE? PRESS ENTER  {}", left);
+    }
+}
+
+fn game_over_input(
+    key_in: Res<Input<KeyCode>>,
+    time_in_state: Res<TimeInState>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if key_in.just_pressed(KeyCode::Return) {
+        scoreboard.continue_game();
+        next_state.set(GameState::Playing);
+    } else if key_in.just_pressed(KeyCode::Escape) || time_in_state.0 > CONTINUE_TIME {
+        next_state.set(GameState::Title);
+    }
+}
@@ -0,0 +1,501 @@
+/*
+The high score table
+
+The best MAX_HIGH_SCORES scores are kept with the initials of the player,
+the wave they reached and the date, in high_scores.ron in the user's data
+directory. A missing file is an empty table. A file that can not be read
+is moved aside to high_scores.corrupt.ron and the table starts empty.
+
+When a game ends with scores good enough for the table, the players enter
+their initials one after the other, with the same steer and flap actions
+as in the arena: steering changes the letter, flapping confirms it.
+
+The table is shown during the attract mode.
+*/
+
+use crate::game_state::{spawn_screen, text, GameState, Screen};
+use crate::level::waves::WaveCounter;
+use crate::player::input::InputBindings;
+use crate::player::player_control::InputSource;
+use crate::player::score::Scoreboard;
+use crate::user_files;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MAX_HIGH_SCORES: usize = 10;
+const FILE_NAME: &str = "high_scores.ron";
+const CORRUPT_FILE_NAME: &str = "high_scores.corrupt.ron";
+const INITIALS_LEN: usize = 3;
+
+pub struct HighScorePlugin;
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_high_scores);
+        app.add_systems(OnEnter(GameState::Attract), spawn_high_score_table);
+        app.add_systems(
+            OnEnter(GameState::EnterInitials),
+            (start_initials_entry, spawn_initials_screen).chain(),
+        );
+        app.add_systems(
+            Update,
+            (initials_input, update_initials_screen)
+                .chain()
+                .run_if(in_state(GameState::EnterInitials)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+impl Date {
+    /// Today in UTC
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Self::from_days_since_epoch(seconds.div_euclid(86_400))
+    }
+
+    /// The proleptic Gregorian date `days` after 1970-01-01
+    /// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self { year, month, day }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: u32,
+    /// The wave the game ended on, counting from 1
+    pub wave: usize,
+    pub date: Date,
+}
+
+/// Best scores first
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    pub entries: Vec<HighScore>,
+}
+impl HighScoreTable {
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_HIGH_SCORES
+                || self.entries.last().is_some_and(|e| score > e.score))
+    }
+
+    /// Which of the scores, sorted best first, are still in the table once
+    /// the ones before them are in
+    pub fn qualifies_in_turn(&self, scores: &[u32]) -> Vec<bool> {
+        let mut table = self.clone();
+        scores
+            .iter()
+            .map(|&score| {
+                table.qualifies(score)
+                    && table
+                        .insert(HighScore {
+                            initials: String::new(),
+                            score,
+                            wave: 0,
+                            date: Date::from_days_since_epoch(0),
+                        })
+                        .is_some()
+            })
+            .collect()
+    }
+
+    /// Adds the entry below any equal scores, returns its place in the table
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let place = self.entries.iter().position(|e| e.score < entry.score).unwrap_or(self.entries.len());
+        if place >= MAX_HIGH_SCORES {
+            return None;
+        }
+        self.entries.insert(place, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        Some(place)
+    }
+
+    /// Reads the table, falling back to an empty table when the file is
+    /// missing or unreadable
+    fn load(path: &Path) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!(target: "joust::high_scores", "Could not read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        match ron::from_str::<Self>(&contents) {
+            Ok(mut table) => {
+                table.tidy();
+                table
+            }
+            Err(e) => {
+                let backup = path.with_file_name(CORRUPT_FILE_NAME);
+                warn!(
+                    target: "joust::high_scores",
+                    "{} is corrupt, moving it to {}: {}",
+                    path.display(),
+                    backup.display(),
+                    e
+                );
+                if let Err(e) = std::fs::rename(path, &backup) {
+                    warn!(target: "joust::high_scores", "Could not move {}: {}", path.display(), e);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        user_files::write_atomically(path, contents.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// Keeps a hand edited file within the rules of the table
+    fn tidy(&mut self) {
+        for e in self.entries.iter_mut() {
+            e.initials = sanitize_initials(&e.initials);
+        }
+        self.entries.sort_by_key(|e| Reverse(e.score));
+        self.entries.truncate(MAX_HIGH_SCORES);
+    }
+}
+
+fn sanitize_initials(initials: &str) -> String {
+    let mut s = initials
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .take(INITIALS_LEN)
+        .collect::<String>();
+    while s.len() < INITIALS_LEN {
+        s.push('A');
+    }
+    s
+}
+
+/// Where the table is saved, None if there is no data directory
+#[derive(Resource)]
+struct HighScoreFile(Option<PathBuf>);
+
+fn load_high_scores(mut commands: Commands) {
+    let path = user_files::data_dir().map(|d| d.join(FILE_NAME));
+    let table = match path.as_ref() {
+        Some(p) => HighScoreTable::load(p),
+        None => {
+            warn!(target: "joust::high_scores", "No data directory, high scores will not be saved");
+            HighScoreTable::default()
+        }
+    };
+    commands.insert_resource(table);
+    commands.insert_resource(HighScoreFile(path));
+}
+
+fn save_high_scores(table: &HighScoreTable, file: &HighScoreFile) {
+    let Some(path) = file.0.as_ref() else {
+        return;
+    };
+    match table.save(path) {
+        Ok(()) => info!(target: "joust::high_scores", "Saved high scores to {}", path.display()),
+        Err(e) => error!(target: "joust::high_scores", "Could not save {}: {}", path.display(), e),
+    }
+}
+
+/// Whether any player of the game that just ended made it into the table
+pub fn any_high_score(scoreboard: &Scoreboard, table: &HighScoreTable) -> bool {
+    scoreboard.players.iter().any(|p| table.qualifies(p.score))
+}
+
+fn spawn_high_score_table(mut commands: Commands, table: Res<HighScoreTable>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(20.0),
+                    right: Val::Px(20.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+            Screen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(text("HIGH SCORES", 32.0));
+            if table.entries.is_empty() {
+                parent.spawn(text("NO SCORES YET", 20.0));
+            }
+            for (i, e) in table.entries.iter().enumerate() {
+                parent.spawn(text(
+                    format!(
+                        "{:>2}. {} {:>7}  WAVE {:>2}  {}-{:02}-{:02}",
+                        i + 1,
+                        e.initials,
+                        e.score,
+                        e.wave,
+                        e.date.year,
+                        e.date.month,
+                        e.date.day
+                    ),
+                    20.0,
+                ));
+            }
+        });
+}
+
+/// Initials being entered, one player at a time
+#[derive(Resource)]
+struct InitialsEntry {
+    pending: Vec<PendingInitials>,
+    wave: usize,
+}
+
+struct PendingInitials {
+    player: usize,
+    input_source: InputSource,
+    score: u32,
+    letters: [u8; INITIALS_LEN],
+    cursor: usize,
+}
+
+#[derive(Component)]
+struct InitialsText;
+
+fn start_initials_entry(
+    mut commands: Commands,
+    scoreboard: Res<Scoreboard>,
+    table: Res<HighScoreTable>,
+    waves: Res<WaveCounter>,
+) {
+    // Best scores pick their place first, and can push the others out
+    let mut pending = scoreboard
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| PendingInitials {
+            player: i,
+            input_source: p.input_source,
+            score: p.score,
+            letters: [b'A'; INITIALS_LEN],
+            cursor: 0,
+        })
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|p| Reverse(p.score));
+    let scores = pending.iter().map(|p| p.score).collect::<Vec<_>>();
+    let mut qualifies = table.qualifies_in_turn(&scores).into_iter();
+    pending.retain(|_| qualifies.next().unwrap_or(false));
+    commands.insert_resource(InitialsEntry {
+        pending,
+        wave: waves.current + 1,
+    });
+}
+
+fn spawn_initials_screen(mut commands: Commands) {
+    let screen = spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.7));
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(text("NEW HIGH SCORE", 64.0));
+        parent.spawn((text("", 32.0), InitialsText));
+        parent.spawn(text("STEER TO CHANGE A LETTER, FLAP TO CONFIRM", 24.0));
+    });
+}
+
+fn initials_input(
+    mut entry: ResMut<InitialsEntry>,
+    mut table: ResMut<HighScoreTable>,
+    file: Res<HighScoreFile>,
+    bindings: Res<InputBindings>,
+    key_in: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let wave = entry.wave;
+    let Some(current) = entry.pending.first_mut() else {
+        next_state.set(GameState::GameOver);
+        return;
+    };
+    let actions = bindings.actions(current.input_source, &key_in);
+
+    let letter = &mut current.letters[current.cursor];
+    *letter = match actions.steer_pressed {
+        -1 if *letter == b'A' => b'Z',
+        -1 => *letter - 1,
+        1 if *letter == b'Z' => b'A',
+        1 => *letter + 1,
+        _ => *letter,
+    };
+
+    if !actions.flap {
+        return;
+    }
+    current.cursor += 1;
+    if current.cursor < INITIALS_LEN {
+        return;
+    }
+
+    let done = entry.pending.remove(0);
+    let initials = String::from_utf8_lossy(&done.letters).into_owned();
+    info!(
+        target: "joust::high_scores",
+        "Player {} entered {} for {} points",
+        done.player + 1,
+        initials,
+        done.score
+    );
+    table.insert(HighScore {
+        initials,
+        score: done.score,
+        wave,
+        date: Date::today(),
+    });
+    if entry.pending.is_empty() {
+        save_high_scores(&table, &file);
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn update_initials_screen(entry: Res<InitialsEntry>, mut q: Query<&mut Text, With<InitialsText>>) {
+    if !entry.is_changed() {
+        return;
+    }
+    let Some(current) = entry.pending.first() else {
+        return;
+    };
+    let letters = current
+        .letters
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            if i == current.cursor {
+                format!("[{}]", *l as char)
+            } else {
+                format!(" {} ", *l as char)
+            }
+        })
+        .collect::<String>();
+    for mut t in q.iter_mut() {
+        t.sections[0].value = format!(
+            "PLAYER {}   {}   {}",
+            current.player + 1,
+            current.score,
+            letters
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_date_from_days_since_epoch() {
+    let date = |year, month, day| Date { year, month, day };
+    assert_eq!(Date::from_days_since_epoch(0), date(1970, 1, 1));
+    assert_eq!(Date::from_days_since_epoch(-1), date(1969, 12, 31));
+    // Leap days, including the century rules
+    assert_eq!(Date::from_days_since_epoch(11_016), date(2000, 2, 29));
+    assert_eq!(Date::from_days_since_epoch(19_782), date(2024, 2, 29));
+    assert_eq!(Date::from_days_since_epoch(47_541), date(2100, 3, 1));
+}
+
+#[cfg(test)]
+fn test_entry(initials: &str, score: u32) -> HighScore {
+    HighScore {
+        initials: initials.to_string(),
+        score,
+        wave: 1,
+        date: Date::from_days_since_epoch(0),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_full_table_and_ties() {
+    let mut table = HighScoreTable::default();
+    assert!(!table.qualifies(0));
+    for i in 0..MAX_HIGH_SCORES as u32 {
+        assert!(table.qualifies(100 - i));
+        table.insert(test_entry("AAA", 100 - i));
+    }
+    // The lowest score is 91, a tie does not push it out
+    assert!(!table.qualifies(91));
+    assert_eq!(table.insert(test_entry("NEW", 91)), None);
+    assert!(table.qualifies(92));
+
+    // Ties go below the scores already in the table
+    assert_eq!(table.insert(test_entry("TIE", 95)), Some(6));
+    assert_eq!(table.entries[5].initials, "AAA");
+    assert_eq!(table.entries.len(), MAX_HIGH_SCORES);
+    assert_eq!(table.entries.last().unwrap().score, 92);
+
+    // Both qualify on their own, the better one pushes the other out
+    assert!(table.qualifies(94) && table.qualifies(93));
+    assert_eq!(table.qualifies_in_turn(&[94, 93, 93]), vec![true, false, false]);
+    assert_eq!(table.qualifies_in_turn(&[200, 94]), vec![true, true]);
+    assert_eq!(table.entries.last().unwrap().score, 92);
+}
+
+#[cfg(test)]
+#[test]
+fn test_tidy_hand_edited_table() {
+    let mut table = HighScoreTable {
+        entries: (0..MAX_HIGH_SCORES as u32 + 2)
+            .map(|i| test_entry("ab", i * 10))
+            .chain([test_entry("x-y-z-w", 55)])
+            .collect(),
+    };
+    table.tidy();
+    assert_eq!(table.entries.len(), MAX_HIGH_SCORES);
+    assert!(table.entries.windows(2).all(|w| w[0].score >= w[1].score));
+    assert_eq!(table.entries[0].score, 110);
+    assert!(table.entries.iter().all(|e| e.initials == "ABA" || e.initials == "XYZ"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_load_missing_unreadable_and_corrupt_files() {
+    let dir = std::env::temp_dir().join(format!("joust-high-scores-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(FILE_NAME);
+
+    // Missing
+    assert!(HighScoreTable::load(&path).entries.is_empty());
+
+    // Unreadable, a directory is left where it is
+    std::fs::create_dir(&path).unwrap();
+    assert!(HighScoreTable::load(&path).entries.is_empty());
+    assert!(path.is_dir());
+    std::fs::remove_dir(&path).unwrap();
+
+    // Corrupt, moved aside
+    std::fs::write(&path, "(entries: [oops").unwrap();
+    assert!(HighScoreTable::load(&path).entries.is_empty());
+    assert!(!path.exists());
+    assert_eq!(std::fs::read_to_string(dir.join(CORRUPT_FILE_NAME)).unwrap(), "(entries: [oops");
+
+    // Saved and loaded back
+    let mut table = HighScoreTable::default();
+    table.insert(test_entry("ABC", 3000));
+    table.save(&path).unwrap();
+    assert_eq!(HighScoreTable::load(&path).entries, table.entries);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
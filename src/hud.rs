@@ -0,0 +1,322 @@
+/*
+The in-game HUD, shown in the attract mode and while a game is on
+
+Along the bottom of the window:
+    each player's score, with a mount icon for every life left
+    the wave number, in the middle
+The first player is on the left, the second on the right, in the colour of
+their mount.
+
+Send a HudBanner to show a message across the arena. It fades in, stays for
+a while and fades out, a newer banner replaces it. A banner is shown when a
+wave starts and when a player loses a life.
+
+All of the UI is laid out for a REFERENCE_HEIGHT window and scaled with the
+window height, menus included.
+*/
+
+use crate::entities::rider::RiderKind;
+use crate::entities::spritesheets::SpriteSheetRegistry;
+use crate::game_state::{text, GameState};
+use crate::level::hazards::HazardEvent;
+use crate::level::waves::{StartWaveEvent, WaveCounter};
+use crate::player::player_damage::JoustEvent;
+use crate::player::score::Scoreboard;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// Height of the default window
+const REFERENCE_HEIGHT: f32 = 720.0;
+const MAX_LIFE_ICONS: u32 = 6;
+const LIFE_ICON_SIZE: f32 = 28.0;
+const BANNER_FADE: f32 = 0.4;
+const BANNER_TIME: f32 = 2.5;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        use GameState::*;
+        app.add_event::<HudBanner>();
+        app.add_systems(Update, scale_ui);
+        app.add_systems(OnEnter(Attract), spawn_hud);
+        app.add_systems(OnEnter(Playing), spawn_hud);
+        app.add_systems(OnEnter(Title), despawn_hud);
+        app.add_systems(OnEnter(Join), despawn_hud);
+        app.add_systems(
+            Update,
+            (
+                (wave_banners, unseat_banners),
+                show_banners,
+                fade_banners,
+                update_wave,
+                update_players.run_if(resource_exists::<SpriteSheetRegistry>()),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// A message shown across the arena
+#[derive(Event, Clone, Debug)]
+pub struct HudBanner(pub String);
+
+#[derive(Component)]
+struct Hud;
+
+/// Holds a panel for each player
+#[derive(Component)]
+struct PlayerPanels;
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct BannerText {
+    /// Seconds since the banner was shown
+    age: f32,
+}
+
+fn scale_ui(q_window: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>, mut ui_scale: ResMut<UiScale>) {
+    if let Ok(window) = q_window.get_single() {
+        let scale = (window.height() / REFERENCE_HEIGHT) as f64;
+        if ui_scale.0 != scale {
+            ui_scale.0 = scale;
+        }
+    }
+}
+
+fn spawn_hud(mut commands: Commands, q_hud: Query<(), With<Hud>>) {
+    // Coming back from the pause screen
+    if !q_hud.is_empty() {
+        return;
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::all(Val::Px(16.0)),
+                    ..default()
+                },
+                // Under the screens of the game states
+                z_index: ZIndex::Global(-1),
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        margin: UiRect::top(Val::Percent(20.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        text("", 56.0).with_text_alignment(TextAlignment::Center),
+                        BannerText { age: BANNER_TIME },
+                    ));
+                });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.0),
+                                justify_content: JustifyContent::SpaceBetween,
+                                align_items: AlignItems::FlexEnd,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        PlayerPanels,
+                    ));
+                    // Centered over the panels, so it stays in the middle with one player
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                width: Val::Percent(100.0),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn((text("", 32.0), WaveText));
+                        });
+                });
+        });
+}
+
+fn despawn_hud(mut commands: Commands, q: Query<Entity, With<Hud>>) {
+    for ent in q.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+fn player_color(kind: RiderKind) -> Color {
+    match kind {
+        RiderKind::Yellow => Color::rgb(1.0, 0.9, 0.2),
+        RiderKind::Blue => Color::rgb(0.4, 0.6, 1.0),
+        _ => Color::WHITE,
+    }
+}
+
+/// The kind, score and lives of each player, as the panels show them
+type PanelContents = Vec<(RiderKind, u32, u32)>;
+
+/// Rebuilds the player panels when a score or a life count changes
+fn update_players(
+    mut commands: Commands,
+    q_panels: Query<Entity, With<PlayerPanels>>,
+    scoreboard: Res<Scoreboard>,
+    registry: Res<SpriteSheetRegistry>,
+    mut shown: Local<Option<(Entity, PanelContents)>>,
+) {
+    let Ok(panels) = q_panels.get_single() else {
+        return;
+    };
+    // The scoreboard changes every frame while a respawn timer runs
+    let players = scoreboard
+        .players
+        .iter()
+        .map(|p| (p.kind, p.score, p.lives))
+        .collect::<Vec<_>>();
+    if shown.as_ref().is_some_and(|(e, p)| *e == panels && *p == players) {
+        return;
+    }
+
+    commands.entity(panels).despawn_descendants();
+    commands.entity(panels).with_children(|parent| {
+        for (i, (kind, score, lives)) in players.iter().enumerate() {
+            let color = player_color(*kind);
+            let align = if i % 2 == 0 { AlignItems::FlexStart } else { AlignItems::FlexEnd };
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: align,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let mut label = text(format!("PLAYER {}  {}", i + 1, score), 32.0);
+                    label.text.sections[0].style.color = color;
+                    parent.spawn(label);
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                column_gap: Val::Px(4.0),
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            let sheet = kind.sprite_sheet();
+                            if let (Some(atlas), Some(stand)) = (registry.atlas(sheet), registry.clip(sheet, "stand")) {
+                                for _ in 0..(*lives).min(MAX_LIFE_ICONS) {
+                                    parent.spawn(AtlasImageBundle {
+                                        style: Style {
+                                            width: Val::Px(LIFE_ICON_SIZE),
+                                            height: Val::Px(LIFE_ICON_SIZE),
+                                            ..default()
+                                        },
+                                        texture_atlas: atlas.clone(),
+                                        texture_atlas_image: UiTextureAtlasImage {
+                                            index: stand.first,
+                                            ..default()
+                                        },
+                                        ..default()
+                                    });
+                                }
+                            }
+                            if *lives > MAX_LIFE_ICONS {
+                                let mut more = text(format!("x{}", lives), 24.0);
+                                more.text.sections[0].style.color = color;
+                                parent.spawn(more);
+                            }
+                        });
+                });
+        }
+    });
+    *shown = Some((panels, players));
+}
+
+fn update_wave(wave: Res<WaveCounter>, mut q: Query<(&mut Text, Ref<WaveText>)>) {
+    for (mut t, marker) in q.iter_mut() {
+        if wave.is_changed() || marker.is_added() {
+            t.sections[0].value = format!("WAVE {}", wave.current + 1);
+        }
+    }
+}
+
+fn wave_banners(mut events: EventReader<StartWaveEvent>, mut banners: EventWriter<HudBanner>) {
+    for e in events.read() {
+        let message = if e.0 == 0 {
+            "PREPARE TO JOUST".to_string()
+        } else {
+            format!("WAVE {}", e.0 + 1)
+        };
+        banners.send(HudBanner(message));
+    }
+}
+
+fn unseat_banners(
+    mut jousts: EventReader<JoustEvent>,
+    mut hazards: EventReader<HazardEvent>,
+    mut banners: EventWriter<HudBanner>,
+) {
+    let unseated = jousts
+        .read()
+        .any(|e| matches!(e, JoustEvent::PlayerUnseated { .. }));
+    let burned = hazards
+        .read()
+        .any(|e| matches!(e, HazardEvent::Burned { player: Some(_), .. }));
+    if unseated || burned {
+        banners.send(HudBanner("BUZZARD BAIT!".to_string()));
+    }
+}
+
+fn show_banners(mut events: EventReader<HudBanner>, mut q: Query<(&mut Text, &mut BannerText)>) {
+    let Some(banner) = events.read().last() else {
+        return;
+    };
+    for (mut t, mut b) in q.iter_mut() {
+        t.sections[0].value = banner.0.clone();
+        b.age = 0.0;
+    }
+}
+
+fn fade_banners(time: Res<Time>, mut q: Query<(&mut Text, &mut BannerText)>) {
+    for (mut t, mut b) in q.iter_mut() {
+        if b.age >= BANNER_TIME {
+            continue;
+        }
+        b.age += time.delta_seconds();
+        let alpha = (b.age / BANNER_FADE)
+            .min((BANNER_TIME - b.age) / BANNER_FADE)
+            .clamp(0.0, 1.0);
+        t.sections[0].style.color.set_a(alpha);
+    }
+}
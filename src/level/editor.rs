@@ -0,0 +1,502 @@
+/*
+In game level editor
+
+Press F2 to toggle the editor while playing. The simulation is paused while
+editing, and is left as it was when the editor is closed. Pausing the game
+closes the editor.
+
+    Left mouse: select and drag platforms and spawn pads
+        Dragging the corner handles of the selected platform edits its collider
+    Mouse wheel: resize the selected platform
+    1 / 2: place a bottom / medium platform at the cursor
+    3: place a spawn pad at the cursor
+    Delete: delete the selection
+    G: toggle snapping to the grid
+    Ctrl + S: save the arena to the current level file
+
+Platforms are spawned with the same PlatformSpawnEvent the game uses,
+so what is edited behaves exactly like the game. Saving rebuilds the
+platforms from the level file, the enemies in the arena are left alone.
+*/
+
+use crate::engine::collision::SquareCollider;
+use crate::engine::physics::{PhysicsScale, Position, V2};
+use crate::engine::simulation_clock::SimulationClock;
+use crate::engine::wraparound::BorderDistance;
+use crate::game_state::GameState;
+use crate::entities::platform::{PlatformKind, PlatformSpawnEvent, PlatformSpawnEventDetails, PlatformSprite};
+use crate::level::waves::WaveList;
+use crate::level::{
+    CurrentLevel, HazardZone, HazardZoneDef, Level, LevelBuiltEvent, PlatformDef, SpawnPad, SpawnPadDef,
+};
+use bevy::asset::io::file::FileAssetReader;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use cgmath::{InnerSpace, Zero};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+/// Snapping grid size, in physics units
+const GRID_SIZE: f32 = 0.5;
+/// Every this many grid cells a grid line is drawn
+const GRID_LINE_EVERY: f32 = 4.0;
+/// How far up and down from the origin the grid is drawn
+const GRID_HALF_HEIGHT: f32 = 32.0;
+const HANDLE_RADIUS: f32 = 0.6;
+const PAD_RADIUS: f32 = 1.0;
+const RESIZE_STEP: f32 = 1.1;
+const MIN_COLLIDER_SIZE: f32 = 0.5;
+
+pub struct LevelEditorPlugin;
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelEditor>();
+        app.add_systems(Update, toggle_editor.run_if(in_state(GameState::Playing)));
+        app.add_systems(OnExit(GameState::Playing), close_editor);
+        app.add_systems(
+            Update,
+            (
+                clear_selection_on_rebuild,
+                editor_mouse,
+                editor_resize,
+                editor_place_and_delete,
+                editor_save,
+                draw_editor,
+            )
+                .chain()
+                .run_if(editor_enabled),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct LevelEditor {
+    pub enabled: bool,
+    pub snap: bool,
+    selected: Option<Entity>,
+    drag: Option<Drag>,
+    /// Whether the simulation was paused when the editor was opened
+    was_paused: bool,
+}
+impl Default for LevelEditor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            snap: true,
+            selected: None,
+            drag: None,
+            was_paused: false,
+        }
+    }
+}
+impl LevelEditor {
+    fn open(&mut self, clock: &mut SimulationClock) {
+        self.enabled = true;
+        self.selected = None;
+        self.drag = None;
+        self.was_paused = clock.is_paused();
+        clock.pause();
+    }
+
+    fn close(&mut self, clock: &mut SimulationClock) {
+        self.enabled = false;
+        self.selected = None;
+        self.drag = None;
+        if !self.was_paused {
+            clock.resume();
+        }
+    }
+
+    fn snap(&self, p: V2) -> V2 {
+        if !self.snap {
+            return p;
+        }
+        V2::new(
+            (p.x / GRID_SIZE).round() * GRID_SIZE,
+            (p.y / GRID_SIZE).round() * GRID_SIZE,
+        )
+    }
+}
+
+enum Drag {
+    /// Moves the selection, keeping the offset from the cursor
+    Move(V2),
+    ColliderMin,
+    ColliderMax,
+}
+
+pub fn editor_enabled(editor: Res<LevelEditor>) -> bool {
+    editor.enabled
+}
+
+fn toggle_editor(
+    key_in: Res<Input<KeyCode>>,
+    mut editor: ResMut<LevelEditor>,
+    mut clock: ResMut<SimulationClock>,
+) {
+    if !key_in.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    if editor.enabled {
+        editor.close(&mut clock);
+    } else {
+        editor.open(&mut clock);
+    }
+    info!(target: "joust::editor", "Level editor enabled: {}", editor.enabled);
+}
+
+fn close_editor(mut editor: ResMut<LevelEditor>, mut clock: ResMut<SimulationClock>) {
+    if editor.enabled {
+        editor.close(&mut clock);
+        info!(target: "joust::editor", "Level editor enabled: false");
+    }
+}
+
+/// The cursor position in physics coordinates
+fn cursor_position(
+    q_window: &Query<&Window, With<PrimaryWindow>>,
+    q_camera: &Query<(&Camera, &GlobalTransform)>,
+    ps: f32,
+) -> Option<V2> {
+    let cursor = q_window.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = q_camera.iter().next()?;
+    let world = camera.viewport_to_world_2d(camera_transform, cursor)?;
+    Some(V2::new(world.x / ps, world.y / ps))
+}
+
+/// The collider bounds of a platform, in physics coordinates
+fn collider_bounds(pos: V2, sc: &SquareCollider) -> (V2, V2) {
+    (pos + sc.offset + sc.min, pos + sc.offset + sc.max)
+}
+
+fn clear_selection_on_rebuild(mut events: EventReader<LevelBuiltEvent>, mut editor: ResMut<LevelEditor>) {
+    if events.read().count() > 0 {
+        editor.selected = None;
+        editor.drag = None;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn editor_mouse(
+    mut editor: ResMut<LevelEditor>,
+    buttons: Res<Input<MouseButton>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    ps: Res<PhysicsScale>,
+    mut q_platforms: Query<(Entity, &mut Position, &mut SquareCollider), (With<PlatformSprite>, Without<SpawnPad>)>,
+    mut q_pads: Query<(Entity, &mut Position), (With<SpawnPad>, Without<PlatformSprite>)>,
+) {
+    let Some(cursor) = cursor_position(&q_window, &q_camera, ps.0) else {
+        return;
+    };
+
+    if buttons.just_pressed(MouseButton::Left) {
+        editor.drag = None;
+        // Collider handles of the selected platform
+        if let Some(Ok((_, pos, sc))) = editor.selected.map(|e| q_platforms.get(e)) {
+            let (min, max) = collider_bounds(pos.0, &sc);
+            if (min - cursor).magnitude() < HANDLE_RADIUS {
+                editor.drag = Some(Drag::ColliderMin);
+            } else if (max - cursor).magnitude() < HANDLE_RADIUS {
+                editor.drag = Some(Drag::ColliderMax);
+            }
+        }
+        if editor.drag.is_none() {
+            let pad = q_pads
+                .iter()
+                .find(|(_, pos)| (pos.0 - cursor).magnitude() < PAD_RADIUS)
+                .map(|(e, pos)| (e, pos.0));
+            let platform = q_platforms
+                .iter()
+                .find(|(_, pos, sc)| {
+                    let (min, max) = collider_bounds(pos.0, sc);
+                    min.x <= cursor.x && cursor.x <= max.x && min.y <= cursor.y && cursor.y <= max.y
+                })
+                .map(|(e, pos, _)| (e, pos.0));
+            editor.selected = None;
+            if let Some((ent, pos)) = pad.or(platform) {
+                editor.selected = Some(ent);
+                editor.drag = Some(Drag::Move(pos - cursor));
+            }
+        }
+    }
+
+    if buttons.just_released(MouseButton::Left) {
+        editor.drag = None;
+    }
+
+    let (Some(selected), Some(drag)) = (editor.selected, editor.drag.as_ref()) else {
+        return;
+    };
+    if !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    match drag {
+        Drag::Move(offset) => {
+            let target = editor.snap(cursor + offset);
+            if let Ok((_, mut pos, _)) = q_platforms.get_mut(selected) {
+                pos.0 = target;
+            } else if let Ok((_, mut pos)) = q_pads.get_mut(selected) {
+                pos.0 = target;
+            }
+        }
+        Drag::ColliderMin => {
+            if let Ok((_, pos, mut sc)) = q_platforms.get_mut(selected) {
+                let local = editor.snap(cursor - pos.0 - sc.offset);
+                sc.min.x = local.x.min(sc.max.x - MIN_COLLIDER_SIZE);
+                sc.min.y = local.y.min(sc.max.y - MIN_COLLIDER_SIZE);
+            }
+        }
+        Drag::ColliderMax => {
+            if let Ok((_, pos, mut sc)) = q_platforms.get_mut(selected) {
+                let local = editor.snap(cursor - pos.0 - sc.offset);
+                sc.max.x = local.x.max(sc.min.x + MIN_COLLIDER_SIZE);
+                sc.max.y = local.y.max(sc.min.y + MIN_COLLIDER_SIZE);
+            }
+        }
+    }
+}
+
+/// Scales the sprite and the collider of the selected platform
+fn editor_resize(
+    editor: Res<LevelEditor>,
+    mut wheel: EventReader<MouseWheel>,
+    mut q: Query<(&mut Transform, &mut SquareCollider), With<PlatformSprite>>,
+) {
+    let scroll: f32 = wheel.read().map(|e| e.y).sum();
+    if scroll == 0.0 {
+        return;
+    }
+    let Some(Ok((mut t, mut sc))) = editor.selected.map(|e| q.get_mut(e)) else {
+        return;
+    };
+    let factor = if scroll > 0.0 { RESIZE_STEP } else { 1.0 / RESIZE_STEP };
+    t.scale *= factor;
+    sc.min *= factor;
+    sc.max *= factor;
+    sc.offset *= factor;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_place_and_delete(
+    mut commands: Commands,
+    mut editor: ResMut<LevelEditor>,
+    key_in: Res<Input<KeyCode>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    ps: Res<PhysicsScale>,
+    q_pads: Query<&SpawnPad>,
+    mut platform_events: EventWriter<PlatformSpawnEvent>,
+) {
+    if key_in.just_pressed(KeyCode::G) {
+        editor.snap = !editor.snap;
+    }
+    if key_in.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        if let Some(ent) = editor.selected.take() {
+            commands.entity(ent).despawn_recursive();
+            editor.drag = None;
+        }
+    }
+
+    let Some(cursor) = cursor_position(&q_window, &q_camera, ps.0) else {
+        return;
+    };
+    let position = editor.snap(cursor);
+    let kind = if key_in.just_pressed(KeyCode::Key1) {
+        Some(PlatformKind::Bottom)
+    } else if key_in.just_pressed(KeyCode::Key2) {
+        Some(PlatformKind::Medium)
+    } else {
+        None
+    };
+    if let Some(kind) = kind {
+        platform_events.send(PlatformSpawnEvent {
+            kind,
+            details: PlatformSpawnEventDetails {
+                position,
+                velocity: V2::zero(),
+            },
+            collider: None,
+            scale: None,
+        });
+    }
+    if key_in.just_pressed(KeyCode::Key3) {
+        let index = q_pads.iter().map(|p| p.index + 1).max().unwrap_or(0);
+        commands.spawn((SpawnPad { index }, Position(position)));
+    }
+}
+
+/// Only what differs from the defaults of the platform kind is written to the level file
+fn platform_def(kind: PlatformKind, position: V2, collider: SquareCollider, scale: f32) -> PlatformDef {
+    PlatformDef {
+        kind,
+        position,
+        collider: (collider != kind.default_collider()).then_some(collider),
+        scale: (scale != kind.sprite_scale()).then_some(scale),
+    }
+}
+
+/// Writes the arena back to the file of the current level
+#[allow(clippy::too_many_arguments)]
+fn editor_save(
+    key_in: Res<Input<KeyCode>>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+    waves: Res<WaveList>,
+    q_platforms: Query<(&PlatformKind, &Position, &SquareCollider, &Transform), With<PlatformSprite>>,
+    q_pads: Query<(&SpawnPad, &Position)>,
+    q_hazards: Query<&HazardZone>,
+) {
+    let ctrl = key_in.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !(ctrl && key_in.just_pressed(KeyCode::S)) {
+        return;
+    }
+    let Some(path) = asset_server.get_path(current.0.id()) else {
+        warn!(target: "joust::editor", "The current level has no path, it can not be saved");
+        return;
+    };
+    let previous = levels.get(&current.0);
+
+    let platforms = q_platforms
+        .iter()
+        .map(|(kind, pos, sc, t)| platform_def(*kind, pos.0, *sc, t.scale.x))
+        .collect();
+
+    let mut pads = q_pads.iter().collect::<Vec<_>>();
+    pads.sort_by_key(|(pad, _)| pad.index);
+
+    let level = Level {
+        name: previous.map(|l| l.name.clone()).unwrap_or_default(),
+        border_distance: previous.and_then(|l| l.border_distance),
+        gravity: previous.and_then(|l| l.gravity),
+        platforms,
+        spawn_pads: pads
+            .into_iter()
+            .map(|(_, pos)| SpawnPadDef { position: pos.0 })
+            .collect(),
+        hazards: q_hazards
+            .iter()
+            .map(|h| HazardZoneDef {
+                kind: h.kind,
+                min: h.min,
+                max: h.max,
+            })
+            .collect(),
+        waves: waves.0.clone(),
+    };
+
+    let file_path = FileAssetReader::get_base_path().join("assets").join(path.path());
+    let result = ron::ser::to_string_pretty(&level, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|s| std::fs::write(&file_path, s).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => info!(target: "joust::editor", "Saved level to {}", file_path.display()),
+        Err(e) => error!(target: "joust::editor", "Could not save level to {}: {}", file_path.display(), e),
+    }
+}
+
+fn draw_editor(
+    mut gizmos: Gizmos,
+    editor: Res<LevelEditor>,
+    ps: Res<PhysicsScale>,
+    border_distance: Res<BorderDistance>,
+    q_platforms: Query<(Entity, &Position, &SquareCollider), With<PlatformSprite>>,
+    q_pads: Query<(Entity, &Position), With<SpawnPad>>,
+    q_hazards: Query<&HazardZone>,
+) {
+    let s = ps.0;
+    let to_screen = |p: V2| Vec2::new(p.x * s, p.y * s);
+    let half_width = border_distance.0 / 2.0;
+
+    // Grid
+    let grid_color = Color::rgba(1.0, 1.0, 1.0, 0.1);
+    let step = GRID_SIZE * GRID_LINE_EVERY;
+    let mut x = -half_width;
+    while x <= half_width {
+        gizmos.line_2d(
+            to_screen(V2::new(x, -GRID_HALF_HEIGHT)),
+            to_screen(V2::new(x, GRID_HALF_HEIGHT)),
+            grid_color,
+        );
+        x += step;
+    }
+    let mut y = -GRID_HALF_HEIGHT;
+    while y <= GRID_HALF_HEIGHT {
+        gizmos.line_2d(
+            to_screen(V2::new(-half_width, y)),
+            to_screen(V2::new(half_width, y)),
+            grid_color,
+        );
+        y += step;
+    }
+
+    for (ent, pos, sc) in q_platforms.iter() {
+        let (min, max) = collider_bounds(pos.0, sc);
+        let selected = editor.selected == Some(ent);
+        let color = if selected { Color::YELLOW } else { Color::WHITE };
+        gizmos.rect_2d(to_screen((min + max) / 2.0), 0.0, to_screen(max - min), color);
+        if selected {
+            gizmos.circle_2d(to_screen(min), HANDLE_RADIUS * s, Color::ORANGE);
+            gizmos.circle_2d(to_screen(max), HANDLE_RADIUS * s, Color::ORANGE);
+        }
+    }
+
+    for (ent, pos) in q_pads.iter() {
+        let color = if editor.selected == Some(ent) {
+            Color::YELLOW
+        } else {
+            Color::LIME_GREEN
+        };
+        gizmos.circle_2d(to_screen(pos.0), PAD_RADIUS * s, color);
+    }
+
+    for h in q_hazards.iter() {
+        gizmos.rect_2d(to_screen((h.min + h.max) / 2.0), 0.0, to_screen(h.max - h.min), Color::RED);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_closing_the_editor_restores_the_clock() {
+    let mut editor = LevelEditor::default();
+    let mut clock = SimulationClock::default();
+    editor.open(&mut clock);
+    assert!(clock.is_paused());
+    editor.close(&mut clock);
+    assert!(!clock.is_paused());
+
+    // Paused with the sim controls before editing
+    clock.pause();
+    editor.open(&mut clock);
+    editor.close(&mut clock);
+    assert!(clock.is_paused());
+}
+
+#[cfg(test)]
+#[test]
+fn test_snaps_to_the_grid() {
+    let mut editor = LevelEditor::default();
+    assert_eq!(editor.snap(V2::new(1.2, -0.3)), V2::new(1.0, -0.5));
+    editor.snap = false;
+    assert_eq!(editor.snap(V2::new(1.2, -0.3)), V2::new(1.2, -0.3));
+}
+
+#[cfg(test)]
+#[test]
+fn test_saved_platforms_keep_only_their_overrides() {
+    let kind = PlatformKind::Medium;
+    let plain = platform_def(kind, V2::new(1.0, 2.0), kind.default_collider(), kind.sprite_scale());
+    assert!(plain.collider.is_none() && plain.scale.is_none());
+
+    let collider = SquareCollider {
+        max: V2::new(9.0, 1.0),
+        ..kind.default_collider()
+    };
+    let edited = platform_def(kind, V2::new(1.0, 2.0), collider, kind.sprite_scale() * 2.0);
+    let saved = ron::ser::to_string_pretty(&edited, ron::ser::PrettyConfig::default()).unwrap();
+    let loaded = ron::de::from_str::<PlatformDef>(&saved).unwrap();
+    assert!(loaded.collider == Some(collider));
+    assert_eq!(loaded.scale, Some(kind.sprite_scale() * 2.0));
+    assert_eq!(loaded.position, V2::new(1.0, 2.0));
+}
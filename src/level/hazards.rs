@@ -0,0 +1,430 @@
+/*
+Hazard zones of the level
+
+Lava fills its zone with flames, over a dark backdrop drawn behind the
+platforms. The flames and the hand are on the Lava layer, behind the
+riders, see engine::render_layer. Riders and eggs that touch the lava burn,
+and so does anything that falls past it. A burned player loses a life, see
+player::player_damage.
+
+Every lava zone has a troll hand. After a cooldown it rises out of the lava
+towards the lowest rider that flies within reach of the surface, grabs it
+and drags it down. A grabbed rider escapes by flapping enough times before
+it reaches the lava. The reach of the hand and the flaps needed to escape
+its grip grow with the wave, see TrollHandCharacteristics in the tuning file.
+
+There is no hand art yet, the hand is drawn as a plain arm.
+*/
+
+use crate::behavior::movement_control::MovementControl;
+use crate::constants::GLOBAL_SPRITE_SCALE;
+use crate::engine::render_layer::{LayerOrder, RenderLayer};
+use crate::engine::physics::{PhysicsScale, PhysicsStages, Position, Velocity, V2};
+use crate::entities::eggman::Eggman;
+use crate::entities::rider::RiderSprite;
+use crate::entities::spritesheets::SpriteSheetRegistry;
+use crate::animation::AnimationTimer;
+use crate::engine::simulation_clock::SimulationClock;
+use crate::level::waves::WaveCounter;
+use crate::level::{HazardKind, HazardZone};
+use crate::player::Player;
+use crate::tuning::Tuning;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const FLAME_SHEET: &str = "flame";
+const FLAME_CLIP: &str = "burn";
+/// Width and height of a flame tile, in world units
+const FLAME_SIZE: f32 = 2.5;
+const LAVA_COLOR: Color = Color::rgb(0.45, 0.05, 0.0);
+const HAND_COLOR: Color = Color::rgb(0.35, 0.45, 0.2);
+const HAND_WIDTH: f32 = 1.2;
+/// The hand rises out of the lava, behind its flames
+const HAND_ORDER: LayerOrder = LayerOrder(0.0);
+const FLAME_ORDER: LayerOrder = LayerOrder(0.5);
+
+pub struct HazardPlugin;
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HazardEvent>();
+        app.add_systems(
+            Update,
+            (
+                dress_lava.run_if(resource_exists::<SpriteSheetRegistry>()),
+                animate_flames.run_if(resource_exists::<SpriteSheetRegistry>()),
+                move_troll_hands.after(PhysicsStages::CalculateNextPositions),
+                grip_riders.after(PhysicsStages::ApplyImpulses),
+                burn_in_lava.after(PhysicsStages::CalculateNextPositions),
+                sync_troll_hand_sprites,
+                log_hazards,
+            )
+                .in_set(HazardSystems),
+        );
+    }
+}
+
+/// Every Update system of the hazard plugin
+/// game_state decides in which states hazards are active
+#[derive(SystemSet, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct HazardSystems;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum HazardEvent {
+    /// A rider or an egg fell into the lava. `player` is set for players
+    Burned { position: V2, player: Option<usize> },
+    /// The troll hand grabbed a rider
+    Grabbed { position: V2 },
+    /// A rider flapped out of the troll hand's grip
+    Escaped { position: V2 },
+}
+
+/// How the troll hand behaves in the first wave, and how that changes
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TrollHandCharacteristics {
+    /// Height above the lava that the hand reaches
+    pub reach: f32,
+    pub reach_per_wave: f32,
+    pub max_reach: f32,
+    /// Flaps needed to escape the grip
+    pub grip: f32,
+    pub grip_per_wave: f32,
+    pub max_grip: f32,
+    pub rise_speed: f32,
+    /// Speed the hand drags a rider down at
+    pub pull_speed: f32,
+    /// Seconds between grabs
+    pub cooldown: f32,
+}
+impl Default for TrollHandCharacteristics {
+    fn default() -> Self {
+        Self {
+            reach: 5.0,
+            reach_per_wave: 0.75,
+            max_reach: 10.0,
+            grip: 3.0,
+            grip_per_wave: 0.5,
+            max_grip: 8.0,
+            rise_speed: 12.0,
+            pull_speed: 2.5,
+            cooldown: 3.0,
+        }
+    }
+}
+impl TrollHandCharacteristics {
+    /// Reach in the wave with this index of the wave list
+    pub fn reach_in_wave(&self, wave: usize) -> f32 {
+        (self.reach + self.reach_per_wave * wave as f32).min(self.max_reach)
+    }
+
+    /// Flaps needed to escape in the wave with this index of the wave list
+    pub fn grip_in_wave(&self, wave: usize) -> u32 {
+        (self.grip + self.grip_per_wave * wave as f32).min(self.max_grip).round() as u32
+    }
+}
+
+/// Sprites that belong to a hazard zone, rebuilt with the level
+#[derive(Component)]
+pub struct HazardSprite;
+
+/// Set on hazard zones once their sprites and hand are spawned
+#[derive(Component)]
+struct Dressed;
+
+#[derive(Component)]
+struct LavaFlame;
+
+#[derive(Component)]
+pub struct TrollHand {
+    zone: Entity,
+    state: TrollHandState,
+}
+
+enum TrollHandState {
+    Hidden { cooldown: Timer },
+    Rising { target: Entity },
+    Gripping { target: Entity, flaps: u32, needed: u32, flapping: bool },
+    Sinking,
+}
+
+/// Fills new lava zones with flames and gives each a troll hand
+fn dress_lava(
+    mut commands: Commands,
+    q_zones: Query<(Entity, &HazardZone), Without<Dressed>>,
+    registry: Res<SpriteSheetRegistry>,
+    tuning: Res<Tuning>,
+    scale: Res<PhysicsScale>,
+) {
+    let mut rng = rand::thread_rng();
+    for (ent, zone) in q_zones.iter() {
+        commands.entity(ent).insert(Dressed);
+        if zone.kind != HazardKind::Lava {
+            continue;
+        }
+
+        let size = zone.max - zone.min;
+        let center = zone.min + size / 2.0;
+        commands.spawn((
+            HazardSprite,
+            Position(center),
+            RenderLayer::Background,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: LAVA_COLOR,
+                    custom_size: Some(Vec2::new(size.x, size.y) * scale.0),
+                    ..default()
+                },
+                transform: Transform::from_xyz(center.x * scale.0, center.y * scale.0, 0.0),
+                ..default()
+            },
+        ));
+
+        if let (Some(atlas), Some(clip)) = (registry.atlas(FLAME_SHEET), registry.clip(FLAME_SHEET, FLAME_CLIP)) {
+            let columns = (size.x / FLAME_SIZE).ceil().max(1.0) as usize;
+            let rows = (size.y / FLAME_SIZE).ceil().max(1.0) as usize;
+            for column in 0..columns {
+                for row in 0..rows {
+                    let position = V2::new(
+                        zone.min.x + (column as f32 + 0.5) * size.x / columns as f32,
+                        zone.max.y - (row as f32 + 0.5) * FLAME_SIZE,
+                    );
+                    commands.spawn((
+                        HazardSprite,
+                        LavaFlame,
+                        Position(position),
+                        RenderLayer::Lava,
+                        FLAME_ORDER,
+                        AnimationTimer(Timer::from_seconds(clip.frame_duration, TimerMode::Repeating)),
+                        SpriteSheetBundle {
+                            texture_atlas: atlas.clone(),
+                            sprite: TextureAtlasSprite::new(rng.gen_range(clip.first..=clip.last)),
+                            transform: Transform {
+                                scale: Vec3::splat(2.5 * GLOBAL_SPRITE_SCALE),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ));
+                }
+            }
+        } else {
+            warn!(target: "joust::assets", "No {} clip in the {} sprite sheet", FLAME_CLIP, FLAME_SHEET);
+        }
+
+        commands.spawn((
+            HazardSprite,
+            TrollHand {
+                zone: ent,
+                state: TrollHandState::Hidden {
+                    cooldown: Timer::from_seconds(tuning.troll_hand.cooldown, TimerMode::Once),
+                },
+            },
+            Position(V2::new(center.x, zone.min.y)),
+            RenderLayer::Lava,
+            HAND_ORDER,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: HAND_COLOR,
+                    anchor: Anchor::TopCenter,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn animate_flames(
+    mut q: Query<(&AnimationTimer, &mut TextureAtlasSprite), With<LavaFlame>>,
+    registry: Res<SpriteSheetRegistry>,
+) {
+    let Some(clip) = registry.clip(FLAME_SHEET, FLAME_CLIP) else {
+        return;
+    };
+    for (timer, mut sprite) in q.iter_mut() {
+        if timer.just_finished() {
+            sprite.index = if sprite.index >= clip.last { clip.first } else { sprite.index + 1 };
+        }
+    }
+}
+
+/// Riders and eggs in the lava burn
+#[allow(clippy::type_complexity)]
+fn burn_in_lava(
+    mut commands: Commands,
+    q_zones: Query<&HazardZone>,
+    q_burnable: Query<(Entity, &Position, Option<&Player>), Or<(With<RiderSprite>, With<Eggman>)>>,
+    mut events: EventWriter<HazardEvent>,
+) {
+    for (ent, pos, player) in q_burnable.iter() {
+        let in_lava = q_zones.iter().any(|zone| {
+            // Anything that falls past the lava has touched it
+            zone.kind == HazardKind::Lava && zone.contains(V2::new(pos.0.x, pos.0.y.max(zone.min.y)))
+        });
+        if in_lava {
+            commands.entity(ent).despawn_recursive();
+            events.send(HazardEvent::Burned {
+                position: pos.0,
+                player: player.map(|p| p.index),
+            });
+        }
+    }
+}
+
+/// Moves the hands through their states
+fn move_troll_hands(
+    mut q_hands: Query<(&mut TrollHand, &mut Position), Without<RiderSprite>>,
+    q_zones: Query<&HazardZone>,
+    q_riders: Query<(Entity, &Position), With<RiderSprite>>,
+    tuning: Res<Tuning>,
+    wave: Res<WaveCounter>,
+    time: Res<SimulationClock>,
+    mut events: EventWriter<HazardEvent>,
+) {
+    let characteristics = &tuning.troll_hand;
+    let reach = characteristics.reach_in_wave(wave.current);
+    let dt = time.delta_seconds();
+    let held = q_hands
+        .iter()
+        .filter_map(|(hand, _)| match hand.state {
+            TrollHandState::Rising { target } | TrollHandState::Gripping { target, .. } => Some(target),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (mut hand, mut pos) in q_hands.iter_mut() {
+        let Ok(zone) = q_zones.get(hand.zone) else {
+            continue;
+        };
+        let surface = zone.max.y;
+        let next = match &mut hand.state {
+            TrollHandState::Hidden { cooldown } => {
+                pos.0.y = zone.min.y;
+                if !cooldown.tick(time.delta()).finished() {
+                    continue;
+                }
+                // The lowest rider within reach that no other hand is after
+                let target = q_riders
+                    .iter()
+                    .filter(|(ent, p)| {
+                        !held.contains(ent)
+                            && zone.min.x <= p.0.x
+                            && p.0.x <= zone.max.x
+                            && p.0.y > surface
+                            && p.0.y < surface + reach
+                    })
+                    .min_by(|(_, a), (_, b)| a.0.y.total_cmp(&b.0.y));
+                match target {
+                    Some((target, p)) => {
+                        pos.0.x = p.0.x;
+                        TrollHandState::Rising { target }
+                    }
+                    None => continue,
+                }
+            }
+            TrollHandState::Rising { target } => {
+                let Ok((_, target_pos)) = q_riders.get(*target) else {
+                    hand.state = TrollHandState::Sinking;
+                    continue;
+                };
+                pos.0.x = target_pos.0.x;
+                pos.0.y += characteristics.rise_speed * dt;
+                if target_pos.0.y > surface + reach {
+                    TrollHandState::Sinking
+                } else if pos.0.y >= target_pos.0.y {
+                    events.send(HazardEvent::Grabbed { position: target_pos.0 });
+                    TrollHandState::Gripping {
+                        target: *target,
+                        flaps: 0,
+                        needed: characteristics.grip_in_wave(wave.current),
+                        flapping: false,
+                    }
+                } else {
+                    continue;
+                }
+            }
+            TrollHandState::Gripping { target, flaps, needed, .. } => {
+                let Ok((_, target_pos)) = q_riders.get(*target) else {
+                    hand.state = TrollHandState::Sinking;
+                    continue;
+                };
+                pos.0 = target_pos.0;
+                if *flaps < *needed {
+                    continue;
+                }
+                events.send(HazardEvent::Escaped { position: target_pos.0 });
+                TrollHandState::Sinking
+            }
+            TrollHandState::Sinking => {
+                pos.0.y -= characteristics.rise_speed * dt;
+                if pos.0.y > zone.min.y {
+                    continue;
+                }
+                TrollHandState::Hidden {
+                    cooldown: Timer::from_seconds(characteristics.cooldown, TimerMode::Once),
+                }
+            }
+        };
+        hand.state = next;
+    }
+}
+
+/// Gripped riders are dragged down, and count their flaps
+fn grip_riders(
+    mut q_hands: Query<&mut TrollHand>,
+    mut q_riders: Query<(&mut Velocity, &MovementControl), With<RiderSprite>>,
+    tuning: Res<Tuning>,
+) {
+    for mut hand in q_hands.iter_mut() {
+        let TrollHandState::Gripping { target, flaps, flapping, .. } = &mut hand.state else {
+            continue;
+        };
+        let Ok((mut vel, mc)) = q_riders.get_mut(*target) else {
+            continue;
+        };
+        vel.0 = V2::new(0.0, -tuning.troll_hand.pull_speed);
+        // A flap pushes up for a single frame
+        let flap = mc.0 .0.y > 0.0;
+        if flap && !*flapping {
+            *flaps += 1;
+        }
+        *flapping = flap;
+    }
+}
+
+fn sync_troll_hand_sprites(
+    mut q: Query<(&TrollHand, &Position, &mut Transform, &mut Sprite, &mut Visibility)>,
+    q_zones: Query<&HazardZone>,
+    scale: Res<PhysicsScale>,
+) {
+    for (hand, pos, mut transform, mut sprite, mut visibility) in q.iter_mut() {
+        let Ok(zone) = q_zones.get(hand.zone) else {
+            continue;
+        };
+        *visibility = match hand.state {
+            TrollHandState::Hidden { .. } => Visibility::Hidden,
+            _ => Visibility::Inherited,
+        };
+        transform.translation.x = pos.0.x * scale.0;
+        transform.translation.y = pos.0.y * scale.0;
+        sprite.custom_size = Some(Vec2::new(HAND_WIDTH, (pos.0.y - zone.min.y).max(0.0)) * scale.0);
+    }
+}
+
+fn log_hazards(mut events: EventReader<HazardEvent>) {
+    for e in events.read() {
+        match *e {
+            HazardEvent::Burned { position, .. } => {
+                debug!(target: "joust::hazards", "Burned in the lava at {:?}", position)
+            }
+            HazardEvent::Grabbed { position } => {
+                debug!(target: "joust::hazards", "The troll hand grabbed a rider at {:?}", position)
+            }
+            HazardEvent::Escaped { position } => {
+                debug!(target: "joust::hazards", "A rider escaped the troll hand at {:?}", position)
+            }
+        }
+    }
+}
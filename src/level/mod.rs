@@ -0,0 +1,281 @@
+/*
+Arenas are described by level files in `assets/levels`, ending in `.level.ron`
+
+A level lists:
+    platforms, by kind, with their positions and optional collider overrides
+    spawn pads, where riders enter the arena
+    the border distance and gravity, which override the tuning file
+    hazard zones, see level::hazards
+    the wave list, see level::waves
+
+Insert a CurrentLevel resource to choose the arena, and send a
+BuildLevelEvent to build the world from it and start the first wave.
+While the arena is running, the platforms, spawn pads and hazards are also
+rebuilt whenever the level changes on disk. The running wave carries on,
+later waves come from the changed wave list.
+
+The border distance and gravity of the level are kept in LevelOverrides,
+so that they still hold when the tuning file is (re)loaded, see tuning.
+*/
+
+pub mod editor;
+pub mod hazards;
+pub mod waves;
+
+use crate::engine::collision::SquareCollider;
+use crate::engine::physics::{Gravity, Position, V2};
+use crate::engine::wraparound::BorderDistance;
+use crate::game_state::arena_running;
+use crate::tuning::Tuning;
+use hazards::HazardSprite;
+use crate::entities::platform::{PlatformKind, PlatformSpawnEvent, PlatformSpawnEventDetails, PlatformSprite};
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+use waves::{StartWaveEvent, WaveDef, WaveList};
+
+pub struct LevelPlugin;
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>();
+        app.init_asset_loader::<LevelLoader>();
+        app.add_event::<BuildLevelEvent>();
+        app.add_event::<LevelBuiltEvent>();
+        app.init_resource::<LevelOverrides>();
+        app.add_plugins((waves::WavePlugin, hazards::HazardPlugin, editor::LevelEditorPlugin));
+        app.add_systems(
+            Update,
+            build_level
+                .run_if(resource_exists::<CurrentLevel>())
+                .run_if(arena_running)
+                .before(waves::WaveSystems),
+        );
+    }
+}
+
+#[derive(Asset, TypePath, Clone, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default)]
+    pub border_distance: Option<f32>,
+    #[serde(default)]
+    pub gravity: Option<V2>,
+    pub platforms: Vec<PlatformDef>,
+    #[serde(default)]
+    pub spawn_pads: Vec<SpawnPadDef>,
+    #[serde(default)]
+    pub hazards: Vec<HazardZoneDef>,
+    #[serde(default)]
+    pub waves: Vec<WaveDef>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlatformDef {
+    pub kind: PlatformKind,
+    pub position: V2,
+    /// When not set, the default collider of the platform kind is used
+    #[serde(default)]
+    pub collider: Option<SquareCollider>,
+    /// When not set, the default sprite scale of the platform kind is used
+    #[serde(default)]
+    pub scale: Option<f32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpawnPadDef {
+    pub position: V2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HazardKind {
+    Lava,
+}
+
+/// An axis aligned region of the arena
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HazardZoneDef {
+    pub kind: HazardKind,
+    pub min: V2,
+    pub max: V2,
+}
+
+/// The level to build the world from
+#[derive(Resource)]
+pub struct CurrentLevel(pub Handle<Level>);
+
+/// World settings of the current level, in place of the ones of the tuning file
+#[derive(Resource, Clone, Copy, Default)]
+pub struct LevelOverrides {
+    pub border_distance: Option<f32>,
+    pub gravity: Option<V2>,
+}
+impl LevelOverrides {
+    pub fn border_distance(&self, tuning: &Tuning) -> f32 {
+        self.border_distance.unwrap_or(tuning.border_distance)
+    }
+
+    pub fn gravity(&self, tuning: &Tuning) -> V2 {
+        self.gravity.unwrap_or(tuning.gravity)
+    }
+}
+
+/// Marks a place where riders enter the arena
+#[derive(Component)]
+pub struct SpawnPad {
+    pub index: usize,
+}
+
+#[derive(Component, Clone)]
+pub struct HazardZone {
+    pub kind: HazardKind,
+    pub min: V2,
+    pub max: V2,
+}
+impl HazardZone {
+    pub fn contains(&self, p: V2) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+}
+
+/// Builds the world from the current level
+#[derive(Event)]
+pub struct BuildLevelEvent;
+
+/// Sent after the world has been (re)built from the current level
+#[derive(Event)]
+pub struct LevelBuiltEvent;
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LevelLoaderError {
+    #[error("Could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Level, LevelLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<Level>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// Despawns the previous arena and builds the arena from the current level
+/// The first wave is only started by a BuildLevelEvent or the first load of the level
+#[allow(clippy::too_many_arguments)]
+fn build_level(
+    mut commands: Commands,
+    mut build_events: EventReader<BuildLevelEvent>,
+    mut events: EventReader<AssetEvent<Level>>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    q_old: Query<Entity, Or<(With<PlatformSprite>, With<SpawnPad>, With<HazardZone>, With<HazardSprite>)>>,
+    mut platform_events: EventWriter<PlatformSpawnEvent>,
+    mut wave_events: EventWriter<StartWaveEvent>,
+    mut built_events: EventWriter<LevelBuiltEvent>,
+    mut overrides: ResMut<LevelOverrides>,
+    mut border_distance: ResMut<BorderDistance>,
+    mut gravity: ResMut<Gravity>,
+    tuning: Res<Tuning>,
+) {
+    let mut start_waves = !build_events.is_empty();
+    let mut should_build = start_waves;
+    build_events.clear();
+    for e in events.read() {
+        match e {
+            AssetEvent::LoadedWithDependencies { id } if *id == current.0.id() => {
+                start_waves = true;
+                should_build = true;
+            }
+            // Edited while it is played, the enemies in the arena are left alone
+            AssetEvent::Modified { id } if *id == current.0.id() => {
+                should_build = true;
+            }
+            _ => {}
+        }
+    }
+    if !should_build {
+        return;
+    }
+    let Some(level) = levels.get(&current.0) else {
+        return;
+    };
+    info!(target: "joust::level", "Building level {}", level.name);
+
+    for ent in q_old.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    *overrides = LevelOverrides {
+        border_distance: level.border_distance,
+        gravity: level.gravity,
+    };
+    border_distance.0 = overrides.border_distance(&tuning);
+    gravity.0 = overrides.gravity(&tuning);
+
+    for p in level.platforms.iter() {
+        platform_events.send(PlatformSpawnEvent {
+            kind: p.kind,
+            details: PlatformSpawnEventDetails {
+                position: p.position,
+                velocity: V2::new(0.0, 0.0),
+            },
+            collider: p.collider,
+            scale: p.scale,
+        });
+    }
+
+    for (index, pad) in level.spawn_pads.iter().enumerate() {
+        commands.spawn((SpawnPad { index }, Position(pad.position)));
+    }
+
+    for h in level.hazards.iter() {
+        commands.spawn(HazardZone {
+            kind: h.kind,
+            min: h.min,
+            max: h.max,
+        });
+    }
+
+    commands.insert_resource(WaveList(level.waves.clone()));
+    if start_waves {
+        wave_events.send(StartWaveEvent(0));
+    }
+    built_events.send(LevelBuiltEvent);
+}
+
+#[cfg(test)]
+#[test]
+fn test_level_overrides_win_over_tuning() {
+    let tuning = Tuning::default();
+    let none = LevelOverrides::default();
+    assert_eq!(none.border_distance(&tuning), tuning.border_distance);
+    assert_eq!(none.gravity(&tuning), tuning.gravity);
+
+    let some = LevelOverrides {
+        border_distance: Some(30.0),
+        gravity: Some(V2::new(0.0, -3.0)),
+    };
+    assert_eq!(some.border_distance(&tuning), 30.0);
+    assert_eq!(some.gravity(&tuning), V2::new(0.0, -3.0));
+}
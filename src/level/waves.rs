@@ -0,0 +1,123 @@
+/*
+Waves of enemies
+
+Each wave of the current level lists the enemies that enter the arena.
+Enemies are spread over the spawn pads in order. Once every enemy of
+a wave is gone, the next wave starts.
+*/
+
+use crate::engine::physics::{Position, V2};
+use crate::entities::pter::{PterSpawnEvent, PterSpawnEventDetails, PterSprite};
+use crate::entities::rider::{RiderKind, RiderSpawnEvent, RiderSpawnEventDetails, RiderSprite};
+use crate::level::SpawnPad;
+use crate::player::player_control::PlayerController;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct WavePlugin;
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StartWaveEvent>();
+        app.init_resource::<WaveList>();
+        app.init_resource::<WaveCounter>();
+        app.add_systems(Update, (start_wave, advance_waves).chain().in_set(WaveSystems));
+    }
+}
+
+#[derive(SystemSet, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct WaveSystems;
+
+/// The enemies of the arcade game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnemyKind {
+    Bounder,
+    Hunter,
+    ShadowLord,
+    Pterodactyl,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WaveDef {
+    pub enemies: Vec<EnemyKind>,
+}
+
+/// The waves of the current level
+#[derive(Resource, Default)]
+pub struct WaveList(pub Vec<WaveDef>);
+
+/// Which wave is being played
+#[derive(Resource, Default)]
+pub struct WaveCounter {
+    /// Index into the wave list
+    pub current: usize,
+    /// Set when a wave starts, until its first enemy has been spawned
+    awaiting_spawn: bool,
+}
+
+/// Starts the wave at this index of the wave list
+#[derive(Event)]
+pub struct StartWaveEvent(pub usize);
+
+#[allow(clippy::too_many_arguments)]
+fn start_wave(
+    mut events: EventReader<StartWaveEvent>,
+    waves: Res<WaveList>,
+    mut counter: ResMut<WaveCounter>,
+    q_pads: Query<(&SpawnPad, &Position)>,
+    mut riders: EventWriter<RiderSpawnEvent>,
+    mut pter: EventWriter<PterSpawnEvent>,
+) {
+    for e in events.read() {
+        let Some(wave) = waves.0.get(e.0) else {
+            continue;
+        };
+        counter.current = e.0;
+        counter.awaiting_spawn = !wave.enemies.is_empty();
+        info!(target: "joust::waves", "Starting wave {}", e.0 + 1);
+
+        let mut pads = q_pads.iter().collect::<Vec<_>>();
+        pads.sort_by_key(|(pad, _)| pad.index);
+
+        for (i, enemy) in wave.enemies.iter().enumerate() {
+            let position = if pads.is_empty() {
+                V2::new(0.0, 0.0)
+            } else {
+                pads[i % pads.len()].1 .0
+            };
+            let details = RiderSpawnEventDetails {
+                position,
+                ..Default::default()
+            };
+            match enemy {
+                EnemyKind::Bounder => riders.send(RiderSpawnEvent::new(RiderKind::Red, details)),
+                EnemyKind::Hunter => riders.send(RiderSpawnEvent::new(RiderKind::Grey, details)),
+                EnemyKind::ShadowLord => riders.send(RiderSpawnEvent::new(RiderKind::Indigo, details)),
+                EnemyKind::Pterodactyl => pter.send(PterSpawnEvent(PterSpawnEventDetails {
+                    pos: position,
+                    vel: V2::new(10.0, 0.0),
+                })),
+            }
+        }
+    }
+}
+
+/// Starts the next wave once every enemy of this wave is gone
+fn advance_waves(
+    mut counter: ResMut<WaveCounter>,
+    waves: Res<WaveList>,
+    q_enemies: Query<(), Or<(With<PterSprite>, (With<RiderSprite>, Without<PlayerController>))>>,
+    mut events: EventWriter<StartWaveEvent>,
+) {
+    let enemies_left = q_enemies.iter().count();
+    if counter.awaiting_spawn {
+        if enemies_left > 0 {
+            counter.awaiting_spawn = false;
+        }
+        return;
+    }
+    if enemies_left == 0 && counter.current + 1 < waves.0.len() {
+        events.send(StartWaveEvent(counter.current + 1));
+        // Stops the next wave from being started twice
+        counter.awaiting_spawn = true;
+    }
+}
@@ -0,0 +1,135 @@
+/*
+Asset loading state
+
+The game starts in LoadingState::Loading. Plugins add the handles the game
+can not run without to RequiredAssets during Startup. Once every one of
+them, and everything they depend on, has loaded, the state moves to Ready.
+Gameplay spawns are only processed in Ready, so spawn events sent earlier
+wait for the assets instead of failing.
+
+If a required asset fails to load, the state moves to Failed and an error
+screen lists what could not be loaded.
+*/
+
+use bevy::asset::{LoadState, RecursiveDependencyLoadState, UntypedAssetId};
+use bevy::prelude::*;
+
+pub struct LoadingPlugin;
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<LoadingState>();
+        app.init_resource::<RequiredAssets>();
+        app.add_systems(
+            Update,
+            check_required_assets.run_if(in_state(LoadingState::Loading)),
+        );
+        app.add_systems(OnEnter(LoadingState::Ready), log_ready);
+        app.add_systems(OnEnter(LoadingState::Failed), show_error_screen);
+    }
+}
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadingState {
+    #[default]
+    Loading,
+    /// Every required asset has loaded
+    Ready,
+    /// A required asset could not be loaded
+    Failed,
+}
+
+/// Assets that have to load before the game can start
+#[derive(Resource, Default)]
+pub struct RequiredAssets {
+    handles: Vec<UntypedHandle>,
+    failed: Vec<String>,
+}
+impl RequiredAssets {
+    pub fn add<A: Asset>(&mut self, handle: Handle<A>) {
+        self.handles.push(handle.untyped());
+    }
+}
+
+/// Adds a spawn event that is not cleared every frame. Its events wait until
+/// the listener runs in LoadingState::Ready, however long the assets take,
+/// and the listener drains them.
+pub fn add_held_spawn_event<T: Event>(app: &mut App) {
+    app.init_resource::<Events<T>>();
+}
+
+fn check_required_assets(
+    mut required: ResMut<RequiredAssets>,
+    asset_server: Res<AssetServer>,
+    mut next_state: ResMut<NextState<LoadingState>>,
+) {
+    if required.handles.is_empty() {
+        return;
+    }
+
+    let describe = |id: UntypedAssetId| {
+        asset_server
+            .get_path(id)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| format!("{:?}", id))
+    };
+
+    let mut failed = Vec::new();
+    let mut all_loaded = true;
+    for handle in required.handles.iter() {
+        let id = handle.id();
+        let own = asset_server.get_load_state(id);
+        let dependencies = asset_server.get_recursive_dependency_load_state(id);
+        if own == Some(LoadState::Failed) {
+            failed.push(describe(id));
+        } else if dependencies == Some(RecursiveDependencyLoadState::Failed) {
+            failed.push(format!("{}, or a file it uses", describe(id)));
+        } else if dependencies != Some(RecursiveDependencyLoadState::Loaded) {
+            all_loaded = false;
+        }
+    }
+
+    if !failed.is_empty() {
+        for f in failed.iter() {
+            error!(target: "joust::assets", "Could not load {}", f);
+        }
+        required.failed = failed;
+        next_state.set(LoadingState::Failed);
+    } else if all_loaded {
+        next_state.set(LoadingState::Ready);
+    }
+}
+
+fn log_ready(required: Res<RequiredAssets>) {
+    info!(target: "joust::assets", "Loaded {} required assets", required.handles.len());
+}
+
+fn show_error_screen(mut commands: Commands, required: Res<RequiredAssets>) {
+    let mut message = String::from("Some game files could not be loaded:\n\n");
+    for f in required.failed.iter() {
+        message.push_str(&format!("    {}\n", f));
+    }
+    message.push_str("\nCheck that the assets folder is complete. The log has more details.");
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.1, 0.0, 0.0)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                message,
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
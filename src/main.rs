@@ -7,103 +7,112 @@ mod constants;
 mod entities;
 mod behavior;
 mod player;
+mod diagnostics;
+mod tuning;
+mod level;
+mod loading;
+mod game_state;
+mod high_scores;
+mod user_files;
+mod settings;
+mod audio;
+mod hud;
+mod camera_effects;
+#[cfg(debug_assertions)]
+mod debug;
 
 use crate::engine::DefaultEnginePlugins;
-use crate::engine::physics::V2;
+use crate::engine::render_layer::CAMERA_Z;
 use crate::player::PlayerPluginGroup;
 use crate::behavior::BehaviorPlugins;
 use animation::AnimationPlugin;
 use bevy::prelude::*;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use entities::{spritesheets::SpriteSheetPluginState, rider::{BlueRiderSpawnEvent, YellowRiderSpawnEvent, RiderSpawnEventDetails}, platform::{MediumPlatformSpawnEvent, PlatformSpawnEventDetails, BottomPlatformSpawnEvent}, JoustEntitiesPlugins, pter::PterSpawnEvent};
-use player::{player_control::PlayerControllerBundle, PlayerBundle};
+use loading::{LoadingPlugin, RequiredAssets};
+use game_state::GameStatePlugin;
+use high_scores::HighScorePlugin;
+use settings::{Settings, SettingsPlugin};
+use audio::JoustAudioPlugin;
+use hud::HudPlugin;
+use camera_effects::CameraEffectsPlugin;
+use entities::JoustEntitiesPlugins;
+use level::{CurrentLevel, LevelPlugin};
 
 fn main() {
-    App::new()
-//        .add_plugins(WindowPlugin {
-//            primary_window: Some(Window{
-//                resolution: (700., 700.).into(),
-//                resizable: false,
-//                ..default()
-//            }),
-//            ..default()
-//        })
-        .add_plugins(DefaultPlugins)
+    // Read before the window opens, so it opens with the right mode and size
+    let settings = Settings::load();
+    let mut default_plugins = DefaultPlugins
+        .set(WindowPlugin {
+            primary_window: Some(settings.window()),
+            ..default()
+        })
+        .build();
+    if !audio::audio_enabled() {
+        default_plugins = default_plugins.disable::<bevy::audio::AudioPlugin>();
+    }
+    let mut app = App::new();
+    app
+        .add_plugins(default_plugins)
+        .insert_resource(settings)
+        // Window, volume, bindings, difficulty and screen shake, with an options menu
+        .add_plugins(SettingsPlugin)
+        // Sound effects
+        .add_plugins(JoustAudioPlugin)
 
+        // Waits for the assets before the game starts
+        .add_plugins(LoadingPlugin)
+        // Title screen, attract mode, playing and game over
+        .add_plugins(GameStatePlugin)
+        // Best scores, saved in the user's data directory
+        .add_plugins(HighScorePlugin)
+        // Scores, lives, wave and banners
+        .add_plugins(HudPlugin)
+        // Screen shake and hit-stop
+        .add_plugins(CameraEffectsPlugin)
         // Entities
         .add_plugins(JoustEntitiesPlugins)
         // Behavior
         .add_plugins(BehaviorPlugins)
+        // Arena, built from a level file
+        .add_plugins(LevelPlugin)
         // Animation
         .add_plugins(AnimationPlugin)
         // Engine
         .add_plugins(DefaultEnginePlugins)
         // Player control and other player specific plugins
         .add_plugins(PlayerPluginGroup)
+        // Physics and movement tuning file
+        .add_plugins(tuning::TuningPlugin)
         // Debug
         .add_plugins(WorldInspectorPlugin::new())
-        // Setup test scene
+        // Diagnostics overlay
+        .add_plugins(diagnostics::JoustDiagnosticsPlugin)
         .add_systems(
             Startup,
-                (test_spawn_riders,
-                spawn_bottom_platform,
-                spawn_medium_platform,
-                setup_camera,
-                spawn_pter,
-                spawn_eggman)
-        )
-        .run();
+                (load_level,
+                setup_camera)
+        );
+    // Collider and physics overlay
+    #[cfg(debug_assertions)]
+    app.add_plugins(debug::DebugPlugins);
+    app.run();
 }
 
-fn test_spawn_riders(
-    mut event_b: EventWriter<BlueRiderSpawnEvent>,
-    mut event_y: EventWriter<YellowRiderSpawnEvent>,
+fn load_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut required: ResMut<RequiredAssets>,
 ) {
-    for x in 0..1 {
-        event_b.send(BlueRiderSpawnEvent(RiderSpawnEventDetails {
-            position: V2::new(x as f32 * 3.0, 10.0),
-            velocity: V2::new(0.0, 0.0),
-            optional_player: Some(PlayerBundle::default()),
-        }));
-    }
-    //    event_y.send(YellowRiderSpawnEvent(RiderSpawnEventDetails {
-    //        position: V2::new(-5.0, 5.0),
-    //        velocity: V2::new(0.0, 0.0),
-    //        ..Default::default()
-    //    }));
-}
-
-fn spawn_medium_platform(mut event_w: EventWriter<MediumPlatformSpawnEvent>) {
-    event_w.send(MediumPlatformSpawnEvent(PlatformSpawnEventDetails {
-        position: V2::new(15.0,15.0),
-        velocity: V2::new(0.0,0.0)
-    }))
-}
-
-fn spawn_bottom_platform(mut event_w: EventWriter<BottomPlatformSpawnEvent>) {
-    event_w.send(BottomPlatformSpawnEvent(PlatformSpawnEventDetails {
-        position: V2::new(0.0, -22.0),
-        velocity: V2::new(0.0, 0.0),
-    }))
+    let level: Handle<level::Level> = asset_server.load("levels/classic.level.ron");
+    required.add(level.clone());
+    commands.insert_resource(CurrentLevel(level));
 }
 
 fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle {
         projection: OrthographicProjection { near: -1000., far: 1000., ..default()},
+        // In front of every render layer
+        transform: Transform::from_xyz(0.0, 0.0, CAMERA_Z),
         ..default()
     });
 }
-
-fn spawn_pter(mut event_w: EventWriter<PterSpawnEvent>) {
-    event_w.send(PterSpawnEvent(entities::pter::PterSpawnEventDetails{
-        pos: V2::new(0.0,0.0),
-        vel: V2::new(10.0,0.0),
-    }));
-}
-
-fn spawn_eggman(mut event_w: EventWriter<entities::eggman::EggmanSpawnEvent>) {
-    event_w.send(entities::eggman::EggmanSpawnEvent {
-        position: V2::new(8.0,0.0),
-        velocity: V2::new(0.0,0.0),
-    });
-}
@@ -0,0 +1,101 @@
+/*
+Input actions
+
+Keys are never read directly by the game. Each InputSource has a set of
+KeyBindings, and the keys are turned into InputActions: steering and
+flapping. Gameplay and the menus that players drive, like the initials
+entry, read the same actions.
+*/
+
+use crate::player::player_control::InputSource;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct InputPlugin;
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputBindings>();
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub flap: Vec<KeyCode>,
+}
+
+/// The keys of each keyboard InputSource
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    pub wasd: KeyBindings,
+    pub arrows: KeyBindings,
+}
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            wasd: KeyBindings {
+                left: vec![KeyCode::A],
+                right: vec![KeyCode::D],
+                flap: vec![KeyCode::W, KeyCode::Z, KeyCode::X],
+            },
+            arrows: KeyBindings {
+                left: vec![KeyCode::Left],
+                right: vec![KeyCode::Right],
+                flap: vec![KeyCode::Up, KeyCode::ControlRight],
+            },
+        }
+    }
+}
+impl InputBindings {
+    /// None for sources that are not driven by the keyboard
+    pub fn get(&self, source: InputSource) -> Option<&KeyBindings> {
+        match source {
+            InputSource::Wasd => Some(&self.wasd),
+            InputSource::Arrows => Some(&self.arrows),
+            InputSource::Demo => None,
+        }
+    }
+
+    /// Like get, for changing the keys
+    pub fn get_mut(&mut self, source: InputSource) -> Option<&mut KeyBindings> {
+        match source {
+            InputSource::Wasd => Some(&mut self.wasd),
+            InputSource::Arrows => Some(&mut self.arrows),
+            InputSource::Demo => None,
+        }
+    }
+
+    /// What the player using `source` asked for this frame
+    pub fn actions(&self, source: InputSource, key_in: &Input<KeyCode>) -> InputActions {
+        let Some(keys) = self.get(source) else {
+            return InputActions::default();
+        };
+        let direction = |left: bool, right: bool| match (left, right) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+        InputActions {
+            steer: direction(
+                key_in.any_pressed(keys.left.iter().copied()),
+                key_in.any_pressed(keys.right.iter().copied()),
+            ),
+            steer_pressed: direction(
+                key_in.any_just_pressed(keys.left.iter().copied()),
+                key_in.any_just_pressed(keys.right.iter().copied()),
+            ),
+            flap: key_in.any_just_pressed(keys.flap.iter().copied()),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputActions {
+    /// -1 while steering left, 1 while steering right
+    pub steer: i32,
+    /// Like steer, but only on the frame the steering key went down
+    pub steer_pressed: i32,
+    /// A flap key went down this frame
+    pub flap: bool,
+}
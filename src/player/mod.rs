@@ -1,36 +1,60 @@
+pub mod input;
 pub mod player_control;
 pub mod player_damage;
+pub mod score;
 
+use input::InputPlugin;
 use player_control::PlayerControlPlugin;
+use player_damage::PlayerDamagePlugin;
 
 use bevy::{app::PluginGroupBuilder, prelude::*};
 
 use crate::engine::collision::ShouldCalculateNonStaticIntersectionsOn;
 
-use self::player_control::PlayerControllerBundle;
+use self::player_control::{InputSource, PlayerControllerBundle};
 
-#[derive(Component)]
-pub struct Player;
+/// The rider of a player, `index` is the player's place on the Scoreboard
+#[derive(Component, Copy, Clone)]
+pub struct Player {
+    pub index: usize,
+}
 
 /// Insert this into a rider to control it as a player
 #[derive(Bundle, Copy, Clone)]
 pub struct PlayerBundle {
+    player: Player,
     pcb: PlayerControllerBundle,
     intersections: ShouldCalculateNonStaticIntersectionsOn,
 }
+impl PlayerBundle {
+    pub fn new(index: usize, input_source: InputSource) -> Self {
+        Self {
+            player: Player { index },
+            pcb: PlayerControllerBundle::new(input_source),
+            ..Default::default()
+        }
+    }
+}
 impl Default for PlayerBundle {
     fn default() -> Self {
         Self {
+            player: Player { index: 0 },
             pcb: PlayerControllerBundle::default(),
             intersections: ShouldCalculateNonStaticIntersectionsOn,
         }
     }
 }
 
+/// Every Update system of the player plugins, only run while someone is playing
+#[derive(SystemSet, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct PlayerSystems;
+
 pub struct PlayerPluginGroup;
 impl PluginGroup for PlayerPluginGroup {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
+            .add(InputPlugin)
             .add(PlayerControlPlugin)
+            .add(PlayerDamagePlugin)
     }
 }
@@ -1,13 +1,22 @@
 use crate::engine::collision::{Grounded, GroundedState};
 use crate::behavior::movement_control::MovementControl;
 use crate::engine::physics::V2;
+use crate::engine::physics::Position;
+use crate::engine::simulation_clock::SimulationClock;
+use crate::entities::rider::{RiderSprite, Team};
 use crate::entities::rider_physics::RiderSpeedCharacteristics;
+use crate::player::input::InputBindings;
+use crate::player::PlayerSystems;
 use bevy::prelude::*;
+use cgmath::InnerSpace;
+
+/// Seconds between the flaps of a demo rider
+const DEMO_FLAP_INTERVAL: f32 = 0.25;
 
 pub struct PlayerControlPlugin;
 impl Plugin for PlayerControlPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update,apply_input);
+        app.add_systems(Update, (apply_input, apply_demo_input).in_set(PlayerSystems));
     }
 }
 
@@ -17,6 +26,14 @@ pub struct PlayerControllerBundle {
     mc: MovementControl,
     psc: RiderSpeedCharacteristics,
 }
+impl PlayerControllerBundle {
+    pub fn new(input_source: InputSource) -> Self {
+        Self {
+            pc: PlayerController { input_source },
+            ..Default::default()
+        }
+    }
+}
 impl Default for PlayerControllerBundle {
     fn default() -> Self {
         Self {
@@ -27,9 +44,14 @@ impl Default for PlayerControllerBundle {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InputSource {
+    /// The keyboard, see InputBindings for the keys
     Wasd,
+    /// The keyboard, with the second player's keys
+    Arrows,
+    /// Flies after the nearest enemy, for the attract mode
+    Demo,
 }
 
 #[derive(Component, Copy, Clone)]
@@ -46,34 +68,66 @@ fn apply_input(
         &Grounded,
     )>,
     key_in: Res<Input<KeyCode>>,
+    bindings: Res<InputBindings>,
 ) {
-    for (pc, mut mc, psc, grounded) in q.iter_mut() {
-        let mut movement = V2::new(0.0, 0.0);
-        match pc.input_source {
-            InputSource::Wasd => {
-                if key_in.pressed(KeyCode::A) {
-                    movement.x = -1.0;
-                }
-                if key_in.pressed(KeyCode::D) {
-                    movement.x = 1.0;
-                }
-                if key_in.just_pressed(KeyCode::Z) {
-                    movement.y = 1.0;
-                }
-                if key_in.just_pressed(KeyCode::X) {
-                    movement.y = 1.0;
-                }
-                if key_in.just_pressed(KeyCode::W) {
-                    movement.y = 1.0;
-                }
-            }
+    for (pc, mc, psc, grounded) in q.iter_mut() {
+        if pc.input_source == InputSource::Demo {
+            continue;
         }
+        let actions = bindings.actions(pc.input_source, &key_in);
+        let movement = V2::new(actions.steer as f32, if actions.flap { 1.0 } else { 0.0 });
+        apply_movement(&movement, mc, psc, grounded);
+    }
+}
 
+/// Demo riders steer towards the nearest enemy and flap to stay above it
+fn apply_demo_input(
+    mut q: Query<(
+        &PlayerController,
+        &Position,
+        &mut MovementControl,
+        &RiderSpeedCharacteristics,
+        &Grounded,
+    )>,
+    q_enemies: Query<(&Position, &Team), With<RiderSprite>>,
+    time: Res<SimulationClock>,
+    mut since_flap: Local<f32>,
+) {
+    *since_flap += time.delta_seconds();
+    let may_flap = *since_flap >= DEMO_FLAP_INTERVAL;
+    if may_flap {
+        *since_flap = 0.0;
+    }
+
+    for (pc, pos, mc, psc, grounded) in q.iter_mut() {
+        if pc.input_source != InputSource::Demo {
+            continue;
+        }
+        let target = q_enemies
+            .iter()
+            .filter(|(_, team)| **team == Team::Enemies)
+            .map(|(p, _)| p.0)
+            .min_by(|a, b| {
+                (a - pos.0)
+                    .magnitude2()
+                    .total_cmp(&(b - pos.0).magnitude2())
+            })
+            // Nobody to chase, circle the middle of the arena
+            .unwrap_or(V2::new(0.0, 5.0));
+
+        let mut movement = V2::new(0.0, 0.0);
+        if (target.x - pos.0.x).abs() > 0.5 {
+            movement.x = (target.x - pos.0.x).signum();
+        }
+        // Stays a little above the target, the higher lance wins
+        if may_flap && pos.0.y < target.y + 1.0 {
+            movement.y = 1.0;
+        }
         apply_movement(&movement, mc, psc, grounded);
     }
 }
 
-fn apply_movement(
+pub fn apply_movement(
     movement: &V2,
     mut mc: Mut<MovementControl>,
     psc: &RiderSpeedCharacteristics,
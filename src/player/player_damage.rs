@@ -1,12 +1,244 @@
 /*
 Controls behavior of the player when non static collisions occur
 
-For most intersections with other entities, this means that the player dies. 
+For most intersections with other entities, this means a joust. The rider
+whose lance is higher unseats the other one. When neither is clearly
+higher, both riders bounce apart. The knight of a rider is part of the
+rider, see entities::mount, whether it touches or is touched. A rider and
+its knight touching the same thing in one frame is one collision.
+    An unseated enemy scores for the player and leaves an egg behind
+    An unseated player loses a life
+Either way the unseated knight's mount flies off without them.
 
 For collisions with other entities, such as the unhatched eggs, the collision triggers a special
-event with that entity.
+event with that entity. Pterodactyls always unseat the player.
+
+Players burned by the lava lose a life too, see level::hazards.
 
 */
 
+use crate::behavior::eggman_control::{DeadEggman, EggmanState};
+use crate::engine::collision::NonStaticCollisionEvent;
+use crate::engine::impulse::ImpulseEvent;
+use crate::engine::physics::{Position, Velocity, V2};
+use crate::engine::simulation_clock::SimulationClock;
+use crate::entities::eggman::EggmanSpawnEvent;
+use crate::entities::mount::{unseat, Knight};
+use crate::entities::pter::PterSprite;
+use crate::entities::rider::{RiderKind, RiderSpawnEvent, RiderSpawnEventDetails, Team};
+use crate::level::hazards::HazardEvent;
+use crate::level::SpawnPad;
+use crate::player::score::{unseat_points, Scoreboard, EGG_POINTS, RESPAWN_DELAY};
+use crate::player::{Player, PlayerBundle, PlayerSystems};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+/// Lances closer in height than this are a tie
+const JOUST_TIE_HEIGHT: f32 = 0.2;
+/// Impulse given to both riders of a tie
+const BOUNCE_IMPULSE: f32 = 60.0;
+
+pub struct PlayerDamagePlugin;
+impl Plugin for PlayerDamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<JoustEvent>();
+        app.init_resource::<Scoreboard>();
+        app.add_systems(
+            Update,
+            (resolve_collisions, burn_players, log_jousts, respawn_players)
+                .chain()
+                .in_set(PlayerSystems),
+        );
+    }
+}
+
+/// The outcome of a collision involving a player
+#[derive(Event, Clone, Copy, Debug)]
+pub enum JoustEvent {
+    /// Neither lance was higher, both riders bounced apart
+    Bounce { position: V2 },
+    /// The player unseated an enemy rider
+    EnemyUnseated { player: usize, position: V2 },
+    /// The player was unseated and lost a life, by a rider or a pterodactyl
+    PlayerUnseated { player: usize, position: V2, by_pter: bool },
+    /// The player crushed an egg
+    EggCrushed { player: usize, position: V2 },
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resolve_collisions(
+    mut commands: Commands,
+    q_collisions: Query<(Entity, &NonStaticCollisionEvent), Changed<NonStaticCollisionEvent>>,
+    q_players: Query<(&Player, &Position, &Velocity)>,
+    q_riders: Query<(&RiderKind, &Team, &Position, &Velocity)>,
+    q_knights: Query<&Parent, With<Knight>>,
+    q_pters: Query<(), With<PterSprite>>,
+    q_eggmen: Query<&EggmanState, Without<DeadEggman>>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut impulses: EventWriter<ImpulseEvent>,
+    mut eggmen: EventWriter<EggmanSpawnEvent>,
+    mut jousts: EventWriter<JoustEvent>,
+) {
+    // Entities already dealt with this frame
+    let mut resolved = HashSet::new();
+    // Riders that already met this frame
+    let mut met = HashSet::new();
+
+    for (hit, coll) in q_collisions.iter() {
+        // A knight is part of the rider it sits on
+        let ent = q_knights.get(hit).map(|p| p.get()).unwrap_or(hit);
+        let other = q_knights.get(coll.0).map(|p| p.get()).unwrap_or(coll.0);
+        let Ok((player, pos, vel)) = q_players.get(ent) else {
+            continue;
+        };
+        if resolved.contains(&ent) || resolved.contains(&other) || !met.insert((ent, other)) {
+            continue;
+        }
+
+        if let Ok(es) = q_eggmen.get(other) {
+            if es.is_crushable() {
+                resolved.insert(other);
+                commands.entity(other).insert(DeadEggman);
+                add_points(&mut scoreboard, player.index, EGG_POINTS);
+                jousts.send(JoustEvent::EggCrushed {
+                    player: player.index,
+                    position: pos.0,
+                });
+            }
+            continue;
+        }
+
+        let by_pter = q_pters.contains(other);
+        let player_lost = if by_pter {
+            true
+        } else if let Ok((kind, team, other_pos, other_vel)) = q_riders.get(other) {
+            if *team != Team::Enemies {
+                continue;
+            }
+            let height = pos.0.y - other_pos.0.y;
+            if height.abs() < JOUST_TIE_HEIGHT {
+                // Only bounces riders that are still closing in,
+                // the intersection lasts for a few frames
+                let dx = pos.0.x - other_pos.0.x;
+                if (vel.0.x - other_vel.0.x) * dx < 0.0 {
+                    let away = V2::new(dx, 0.0);
+                    impulses.send(ImpulseEvent::knockback(ent, away, BOUNCE_IMPULSE));
+                    impulses.send(ImpulseEvent::knockback(other, -away, BOUNCE_IMPULSE));
+                    jousts.send(JoustEvent::Bounce { position: pos.0 });
+                }
+                continue;
+            }
+            if height > 0.0 {
+                resolved.insert(other);
+                unseat(&mut commands, other, other_pos.0);
+                eggmen.send(EggmanSpawnEvent {
+                    position: other_pos.0,
+                    velocity: other_vel.0,
+                });
+                add_points(&mut scoreboard, player.index, unseat_points(*kind));
+                jousts.send(JoustEvent::EnemyUnseated {
+                    player: player.index,
+                    position: other_pos.0,
+                });
+                continue;
+            }
+            true
+        } else {
+            false
+        };
+
+        if player_lost {
+            resolved.insert(ent);
+            unseat(&mut commands, ent, pos.0);
+            lose_life(&mut scoreboard, player.index);
+            jousts.send(JoustEvent::PlayerUnseated {
+                player: player.index,
+                position: pos.0,
+                by_pter,
+            });
+        }
+    }
+}
+
+fn log_jousts(mut events: EventReader<JoustEvent>) {
+    for e in events.read() {
+        match *e {
+            JoustEvent::Bounce { position } => {
+                debug!(target: "joust::score", "Riders bounced apart at {:?}", position)
+            }
+            JoustEvent::EnemyUnseated { player, position } => {
+                debug!(target: "joust::score", "Player {} unseated an enemy at {:?}", player + 1, position)
+            }
+            JoustEvent::PlayerUnseated { player, position, by_pter } => {
+                let by = if by_pter { "a pterodactyl" } else { "a rider" };
+                debug!(target: "joust::score", "Player {} was unseated by {} at {:?}", player + 1, by, position)
+            }
+            JoustEvent::EggCrushed { player, position } => {
+                debug!(target: "joust::score", "Player {} crushed an egg at {:?}", player + 1, position)
+            }
+        }
+    }
+}
+
+/// The hazard has already despawned the rider
+fn burn_players(mut events: EventReader<HazardEvent>, mut scoreboard: ResMut<Scoreboard>) {
+    for e in events.read() {
+        if let HazardEvent::Burned { player: Some(player), position } = *e {
+            debug!(target: "joust::score", "Player {} burned at {:?}", player + 1, position);
+            lose_life(&mut scoreboard, player);
+        }
+    }
+}
+
+/// Starts the respawn timer when the player has lives left
+fn lose_life(scoreboard: &mut Scoreboard, player: usize) {
+    if let Some(p) = scoreboard.players.get_mut(player) {
+        p.lives = p.lives.saturating_sub(1);
+        if p.lives > 0 {
+            p.respawn = Some(Timer::from_seconds(RESPAWN_DELAY, TimerMode::Once));
+        }
+    }
+}
+
+fn add_points(scoreboard: &mut Scoreboard, player: usize, points: u32) {
+    if let Some(p) = scoreboard.players.get_mut(player) {
+        p.score += points;
+    }
+}
+
+/// Brings players with lives left back into the arena on a spawn pad
+fn respawn_players(
+    mut scoreboard: ResMut<Scoreboard>,
+    time: Res<SimulationClock>,
+    q_pads: Query<(&SpawnPad, &Position)>,
+    mut riders: EventWriter<RiderSpawnEvent>,
+) {
+    let mut pads = q_pads.iter().collect::<Vec<_>>();
+    pads.sort_by_key(|(pad, _)| pad.index);
 
+    for (index, p) in scoreboard.players.iter_mut().enumerate() {
+        let Some(timer) = p.respawn.as_mut() else {
+            continue;
+        };
+        if !timer.tick(time.delta()).finished() {
+            continue;
+        }
+        p.respawn = None;
 
+        let position = if pads.is_empty() {
+            V2::new(0.0, 0.0)
+        } else {
+            pads[index % pads.len()].1 .0
+        };
+        riders.send(RiderSpawnEvent {
+            controller: Some(PlayerBundle::new(index, p.input_source)),
+            ..RiderSpawnEvent::new(
+                p.kind,
+                RiderSpawnEventDetails {
+                    position,
+                    ..Default::default()
+                },
+            )
+        });
+    }
+}
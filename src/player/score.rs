@@ -0,0 +1,111 @@
+/*
+Score and lives of everyone taking part in the current game
+
+Players are added to the Scoreboard when they join. A player whose rider is
+unseated loses a life and comes back after RESPAWN_DELAY, until no lives
+are left.
+*/
+
+use crate::entities::rider::RiderKind;
+use crate::player::player_control::InputSource;
+use bevy::prelude::*;
+
+/// Lives on the normal difficulty
+pub const STARTING_LIVES: u32 = 3;
+/// Seconds between losing a life and the next rider entering the arena
+pub const RESPAWN_DELAY: f32 = 2.0;
+/// Points for crushing an egg before it hatches
+pub const EGG_POINTS: u32 = 250;
+
+#[derive(Resource)]
+pub struct Scoreboard {
+    pub players: Vec<PlayerScore>,
+    /// Lives of every player when they join or continue
+    pub starting_lives: u32,
+}
+impl Default for Scoreboard {
+    fn default() -> Self {
+        Self::new(STARTING_LIVES)
+    }
+}
+impl Scoreboard {
+    pub fn new(starting_lives: u32) -> Self {
+        Self {
+            players: Vec::new(),
+            starting_lives,
+        }
+    }
+
+    /// Adds a player with a full set of lives, who rides in after RESPAWN_DELAY
+    /// Returns the player's index
+    pub fn join(&mut self, input_source: InputSource, kind: RiderKind) -> usize {
+        self.players.push(PlayerScore {
+            input_source,
+            kind,
+            score: 0,
+            lives: self.starting_lives,
+            respawn: Some(Timer::from_seconds(RESPAWN_DELAY, TimerMode::Once)),
+        });
+        self.players.len() - 1
+    }
+
+    pub fn has_joined(&self, input_source: InputSource) -> bool {
+        self.players.iter().any(|p| p.input_source == input_source)
+    }
+
+    /// Every player is out of lives
+    pub fn is_game_over(&self) -> bool {
+        !self.players.is_empty() && self.players.iter().all(|p| p.lives == 0)
+    }
+
+    /// Gives every player a fresh set of lives and clears the score
+    pub fn continue_game(&mut self) {
+        for p in self.players.iter_mut() {
+            p.score = 0;
+            p.lives = self.starting_lives;
+            p.respawn = Some(Timer::from_seconds(RESPAWN_DELAY, TimerMode::Once));
+        }
+    }
+}
+
+pub struct PlayerScore {
+    pub input_source: InputSource,
+    /// The mount this player rides
+    pub kind: RiderKind,
+    pub score: u32,
+    /// Riders left, including the one in the arena
+    pub lives: u32,
+    /// Counts down to the next rider entering the arena
+    pub respawn: Option<Timer>,
+}
+
+/// Points for unseating an enemy rider
+pub fn unseat_points(kind: RiderKind) -> u32 {
+    match kind {
+        RiderKind::Red => 500,
+        RiderKind::Grey => 750,
+        RiderKind::Indigo => 1500,
+        // Player mounts, when a team override makes them enemies
+        RiderKind::Yellow | RiderKind::Blue => 1000,
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_game_over_and_continue() {
+    let mut scoreboard = Scoreboard::new(2);
+    assert!(!scoreboard.is_game_over());
+
+    let p1 = scoreboard.join(InputSource::Wasd, RiderKind::Yellow);
+    let p2 = scoreboard.join(InputSource::Arrows, RiderKind::Blue);
+    scoreboard.players[p1].lives = 0;
+    assert!(!scoreboard.is_game_over());
+    scoreboard.players[p2].lives = 0;
+    scoreboard.players[p2].score = 500;
+    assert!(scoreboard.is_game_over());
+
+    scoreboard.continue_game();
+    assert!(!scoreboard.is_game_over());
+    assert_eq!(scoreboard.players[p2].lives, 2);
+    assert_eq!(scoreboard.players[p2].score, 0);
+}
@@ -0,0 +1,249 @@
+/*
+User settings
+
+The Settings resource is read from settings.ron in the user's config
+directory before the app is built, so the window opens with the right mode
+and size. A missing or unreadable file gives the default settings, and
+missing fields keep their defaults, so older files still load.
+
+Changes to the resource are applied straight away: the window, the input
+bindings and the master volume. Only the window fields whose setting
+changed are touched, so a window resized by hand keeps its size when the
+volume is changed. The difficulty is used when the next game
+starts, and screen shake is read every frame. The game has no music yet,
+the music volume is stored so it is there for when it does. The file is
+written when the options menu is closed.
+*/
+
+pub mod options_menu;
+
+use crate::player::input::InputBindings;
+use crate::user_files;
+use bevy::audio::{GlobalVolume, VolumeLevel};
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const FILE_NAME: &str = "settings.ron";
+/// The window size at a resolution scale of 1
+const BASE_RESOLUTION: (f32, f32) = (640.0, 360.0);
+pub const MIN_RESOLUTION_SCALE: f32 = 1.0;
+pub const MAX_RESOLUTION_SCALE: f32 = 4.0;
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>();
+        app.add_plugins(options_menu::OptionsMenuPlugin);
+        app.add_systems(Update, apply_settings.run_if(resource_changed::<Settings>()));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+impl Difficulty {
+    pub fn starting_lives(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 5,
+            Difficulty::Normal => crate::player::score::STARTING_LIVES,
+            Difficulty::Hard => 2,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window_mode: WindowModeSetting,
+    /// The window size is BASE_RESOLUTION times this
+    pub resolution_scale: f32,
+    pub vsync: bool,
+    /// Volumes go from 0 to 1
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    /// There is no music yet, the volume is kept for when there is
+    pub music_volume: f32,
+    pub bindings: InputBindings,
+    pub difficulty: Difficulty,
+    /// Shake the camera on hard hits
+    pub screen_shake: bool,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window_mode: WindowModeSetting::Windowed,
+            resolution_scale: 2.0,
+            vsync: true,
+            master_volume: 1.0,
+            sfx_volume: 0.8,
+            music_volume: 0.6,
+            bindings: InputBindings::default(),
+            difficulty: Difficulty::Normal,
+            screen_shake: true,
+        }
+    }
+}
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        user_files::config_dir().map(|d| d.join(FILE_NAME))
+    }
+
+    /// Reads the settings file, falling back to the defaults
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!(target: "joust::settings", "Could not read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        match ron::from_str::<Self>(&contents) {
+            Ok(settings) => settings.clamped(),
+            Err(e) => {
+                warn!(
+                    target: "joust::settings",
+                    "Could not parse {}, using the default settings: {}",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            warn!(target: "joust::settings", "No config directory, settings will not be saved");
+            return;
+        };
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|s| user_files::write_atomically(&path, s.as_bytes()).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => info!(target: "joust::settings", "Saved settings to {}", path.display()),
+            Err(e) => error!(target: "joust::settings", "Could not save {}: {}", path.display(), e),
+        }
+    }
+
+    /// Keeps hand edited values in range
+    fn clamped(mut self) -> Self {
+        self.resolution_scale = self.resolution_scale.clamp(MIN_RESOLUTION_SCALE, MAX_RESOLUTION_SCALE);
+        self.master_volume = self.master_volume.clamp(0.0, 1.0);
+        self.sfx_volume = self.sfx_volume.clamp(0.0, 1.0);
+        self.music_volume = self.music_volume.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The primary window to open the app with
+    pub fn window(&self) -> Window {
+        let mut window = Window {
+            title: "Joust".to_string(),
+            ..default()
+        };
+        self.apply_to_window(&mut window);
+        window
+    }
+
+    fn apply_to_window(&self, window: &mut Window) {
+        window.mode = self.window_mode.into();
+        self.apply_resolution(window);
+        self.apply_vsync(window);
+    }
+
+    /// Applies the window settings that differ from `previous`
+    fn apply_changes_to_window(&self, previous: &Settings, window: &mut Window) {
+        if self.window_mode != previous.window_mode {
+            window.mode = self.window_mode.into();
+        }
+        if self.resolution_scale != previous.resolution_scale {
+            self.apply_resolution(window);
+        }
+        if self.vsync != previous.vsync {
+            self.apply_vsync(window);
+        }
+    }
+
+    fn apply_resolution(&self, window: &mut Window) {
+        window.resolution.set(
+            BASE_RESOLUTION.0 * self.resolution_scale,
+            BASE_RESOLUTION.1 * self.resolution_scale,
+        );
+    }
+
+    fn apply_vsync(&self, window: &mut Window) {
+        window.present_mode = if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+    }
+}
+
+/// The window was opened with the first settings, see Settings::window
+fn apply_settings(
+    settings: Res<Settings>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    mut bindings: ResMut<InputBindings>,
+    // Missing when running without sound
+    global_volume: Option<ResMut<GlobalVolume>>,
+    mut applied: Local<Option<Settings>>,
+) {
+    if let (Ok(mut window), Some(previous)) = (q_window.get_single_mut(), applied.as_ref()) {
+        settings.apply_changes_to_window(previous, &mut window);
+    }
+    *applied = Some(settings.clone());
+    if *bindings != settings.bindings {
+        *bindings = settings.bindings.clone();
+    }
+    // Only affects sounds that start playing after this
+    if let Some(mut global_volume) = global_volume {
+        global_volume.volume = VolumeLevel::new(settings.master_volume);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_only_changed_window_settings_are_applied() {
+    let previous = Settings::default();
+    let mut window = previous.window();
+    window.resolution.set(1000.0, 500.0);
+
+    let louder = Settings {
+        master_volume: 0.5,
+        ..previous.clone()
+    };
+    louder.apply_changes_to_window(&previous, &mut window);
+    assert_eq!((window.resolution.width(), window.resolution.height()), (1000.0, 500.0));
+
+    let bigger = Settings {
+        resolution_scale: 3.0,
+        ..previous.clone()
+    };
+    bigger.apply_changes_to_window(&previous, &mut window);
+    assert_eq!(window.resolution.width(), BASE_RESOLUTION.0 * 3.0);
+}
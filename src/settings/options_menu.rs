@@ -0,0 +1,258 @@
+/*
+The options menu, opened with O from the title screen or the pause screen
+
+Up and down pick a row, left and right change its value. Enter on a key
+binding waits for the next key press and binds the action to that key,
+Escape cancels. Escape, or Enter on BACK, closes the menu, saves the
+settings and goes back to where the menu was opened from.
+*/
+
+use crate::game_state::{spawn_screen, text, GameState};
+use crate::player::player_control::InputSource;
+use crate::settings::{Difficulty, Settings, WindowModeSetting, MAX_RESOLUTION_SCALE, MIN_RESOLUTION_SCALE};
+use bevy::prelude::*;
+
+const RESOLUTION_STEP: f32 = 0.5;
+const VOLUME_STEP: f32 = 0.1;
+const SELECTED_COLOR: Color = Color::YELLOW;
+
+pub struct OptionsMenuPlugin;
+impl Plugin for OptionsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OptionsMenu>();
+        app.add_systems(OnEnter(GameState::Options), spawn_options_screen);
+        app.add_systems(OnExit(GameState::Options), save_settings);
+        app.add_systems(
+            Update,
+            (options_input, update_options_screen)
+                .chain()
+                .run_if(in_state(GameState::Options)),
+        );
+    }
+}
+
+/// Opens the options menu, which goes back to `from` when it is closed
+pub fn open_options(from: GameState, menu: &mut OptionsMenu, next_state: &mut NextState<GameState>) {
+    *menu = OptionsMenu {
+        return_to: from,
+        ..Default::default()
+    };
+    next_state.set(GameState::Options);
+}
+
+#[derive(Resource)]
+pub struct OptionsMenu {
+    selected: usize,
+    /// Waiting for a key to bind to the selected row
+    rebinding: bool,
+    return_to: GameState,
+}
+impl Default for OptionsMenu {
+    fn default() -> Self {
+        Self {
+            selected: 0,
+            rebinding: false,
+            return_to: GameState::Title,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Left,
+    Right,
+    Flap,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OptionRow {
+    WindowMode,
+    ResolutionScale,
+    Vsync,
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    Difficulty,
+    ScreenShake,
+    Binding(InputSource, Action),
+    Back,
+}
+
+const ROWS: [OptionRow; 15] = [
+    OptionRow::WindowMode,
+    OptionRow::ResolutionScale,
+    OptionRow::Vsync,
+    OptionRow::MasterVolume,
+    OptionRow::SfxVolume,
+    OptionRow::MusicVolume,
+    OptionRow::Difficulty,
+    OptionRow::ScreenShake,
+    OptionRow::Binding(InputSource::Wasd, Action::Left),
+    OptionRow::Binding(InputSource::Wasd, Action::Right),
+    OptionRow::Binding(InputSource::Wasd, Action::Flap),
+    OptionRow::Binding(InputSource::Arrows, Action::Left),
+    OptionRow::Binding(InputSource::Arrows, Action::Right),
+    OptionRow::Binding(InputSource::Arrows, Action::Flap),
+    OptionRow::Back,
+];
+
+/// The text of a row, by its index in ROWS
+#[derive(Component)]
+struct OptionText(usize);
+
+fn spawn_options_screen(mut commands: Commands) {
+    let screen = spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.8));
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(text("OPTIONS", 48.0));
+        for i in 0..ROWS.len() {
+            parent.spawn((text("", 24.0), OptionText(i)));
+        }
+    });
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+fn options_input(
+    key_in: Res<Input<KeyCode>>,
+    mut menu: ResMut<OptionsMenu>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let row = ROWS[menu.selected];
+
+    if menu.rebinding {
+        if key_in.just_pressed(KeyCode::Escape) {
+            menu.rebinding = false;
+        } else if let Some(key) = key_in.get_just_pressed().next() {
+            if let OptionRow::Binding(source, action) = row {
+                if let Some(keys) = settings.bindings.get_mut(source) {
+                    let bound = match action {
+                        Action::Left => &mut keys.left,
+                        Action::Right => &mut keys.right,
+                        Action::Flap => &mut keys.flap,
+                    };
+                    *bound = vec![*key];
+                }
+            }
+            menu.rebinding = false;
+        }
+        return;
+    }
+
+    if key_in.just_pressed(KeyCode::Escape) {
+        next_state.set(menu.return_to);
+        return;
+    }
+    if key_in.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + ROWS.len() - 1) % ROWS.len();
+    }
+    if key_in.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % ROWS.len();
+    }
+
+    let step = match (key_in.just_pressed(KeyCode::Left), key_in.just_pressed(KeyCode::Right)) {
+        (true, false) => -1.0,
+        (false, true) => 1.0,
+        _ => 0.0,
+    };
+    if step != 0.0 {
+        change_value(&mut settings, ROWS[menu.selected], step);
+    }
+
+    if key_in.just_pressed(KeyCode::Return) {
+        match ROWS[menu.selected] {
+            OptionRow::Binding(..) => menu.rebinding = true,
+            OptionRow::Back => next_state.set(menu.return_to),
+            row => change_value(&mut settings, row, 1.0),
+        }
+    }
+}
+
+/// Moves the value of the row one step up or down
+fn change_value(settings: &mut Settings, row: OptionRow, step: f32) {
+    let cycle = |len: usize, i: usize| {
+        if step > 0.0 {
+            (i + 1) % len
+        } else {
+            (i + len - 1) % len
+        }
+    };
+    let volume = |v: f32| (v + step * VOLUME_STEP).clamp(0.0, 1.0);
+    match row {
+        OptionRow::WindowMode => {
+            let modes = [
+                WindowModeSetting::Windowed,
+                WindowModeSetting::Borderless,
+                WindowModeSetting::Fullscreen,
+            ];
+            let i = modes.iter().position(|m| *m == settings.window_mode).unwrap_or(0);
+            settings.window_mode = modes[cycle(modes.len(), i)];
+        }
+        OptionRow::ResolutionScale => {
+            settings.resolution_scale = (settings.resolution_scale + step * RESOLUTION_STEP)
+                .clamp(MIN_RESOLUTION_SCALE, MAX_RESOLUTION_SCALE);
+        }
+        OptionRow::Vsync => settings.vsync = !settings.vsync,
+        OptionRow::MasterVolume => settings.master_volume = volume(settings.master_volume),
+        OptionRow::SfxVolume => settings.sfx_volume = volume(settings.sfx_volume),
+        OptionRow::MusicVolume => settings.music_volume = volume(settings.music_volume),
+        OptionRow::Difficulty => {
+            let levels = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+            let i = levels.iter().position(|d| *d == settings.difficulty).unwrap_or(1);
+            settings.difficulty = levels[cycle(levels.len(), i)];
+        }
+        OptionRow::ScreenShake => settings.screen_shake = !settings.screen_shake,
+        OptionRow::Binding(..) | OptionRow::Back => {}
+    }
+}
+
+fn row_text(row: OptionRow, settings: &Settings) -> String {
+    let percent = |v: f32| format!("{:.0}%", v * 100.0);
+    match row {
+        OptionRow::WindowMode => format!("WINDOW MODE  {:?}", settings.window_mode),
+        OptionRow::ResolutionScale => format!("RESOLUTION SCALE  {:.1}x", settings.resolution_scale),
+        OptionRow::Vsync => format!("VSYNC  {}", if settings.vsync { "ON" } else { "OFF" }),
+        OptionRow::MasterVolume => format!("MASTER VOLUME  {}", percent(settings.master_volume)),
+        OptionRow::SfxVolume => format!("SFX VOLUME  {}", percent(settings.sfx_volume)),
+        OptionRow::MusicVolume => format!("MUSIC VOLUME  {}", percent(settings.music_volume)),
+        OptionRow::Difficulty => format!("DIFFICULTY  {:?}", settings.difficulty),
+        OptionRow::ScreenShake => format!("SCREEN SHAKE  {}", if settings.screen_shake { "ON" } else { "OFF" }),
+        OptionRow::Binding(source, action) => {
+            let player = match source {
+                InputSource::Arrows => 2,
+                _ => 1,
+            };
+            let (name, keys) = match (settings.bindings.get(source), action) {
+                (Some(k), Action::Left) => ("LEFT", &k.left),
+                (Some(k), Action::Right) => ("RIGHT", &k.right),
+                (Some(k), Action::Flap) => ("FLAP", &k.flap),
+                (None, _) => return String::new(),
+            };
+            let keys = keys.iter().map(|k| format!("{:?}", k)).collect::<Vec<_>>().join(", ");
+            format!("PLAYER {} {}  {}", player, name, keys)
+        }
+        OptionRow::Back => "BACK".to_string(),
+    }
+    .to_uppercase()
+}
+
+fn update_options_screen(
+    menu: Res<OptionsMenu>,
+    settings: Res<Settings>,
+    mut q: Query<(&OptionText, &mut Text)>,
+) {
+    if !menu.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for (option, mut t) in q.iter_mut() {
+        let selected = option.0 == menu.selected;
+        let mut value = row_text(ROWS[option.0], &settings);
+        if selected && menu.rebinding {
+            value = "PRESS A KEY, ESCAPE TO CANCEL".to_string();
+        }
+        t.sections[0].value = value;
+        t.sections[0].style.color = if selected { SELECTED_COLOR } else { Color::WHITE };
+    }
+}
@@ -0,0 +1,210 @@
+/*
+Physics and movement tuning
+
+Every value that affects the feel of the game lives in `assets/default.tuning.ron`.
+The file is loaded through the asset server and is applied again whenever
+it changes on disk, so flight feel can be tuned without recompiling.
+
+Until the file has loaded, the Tuning resource holds the built in defaults.
+Entities that are spawned later get the current tuning as soon as their
+tunable components are added. The border distance and gravity of the
+current level win over the ones of the tuning file, see LevelOverrides.
+*/
+
+use crate::behavior::eggman_control::{default_eggman_speed_clamps, EggmanSpeedCharacteristics};
+use crate::behavior::pter_control::{default_pter_speed_clamps, PterSpeedCharacteristics};
+use crate::engine::physics::{Gravity, PhysicsScale, V2};
+use crate::engine::speed_clamps::SpeedClamps;
+use crate::engine::wraparound::BorderDistance;
+use crate::entities::eggman::Eggman;
+use crate::entities::pter::PterSprite;
+use crate::entities::rider_physics::{RiderSpeedCharacteristics, SpeedOverride};
+use crate::level::hazards::TrollHandCharacteristics;
+use crate::level::LevelOverrides;
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+const TUNING_PATH: &str = "default.tuning.ron";
+
+pub struct TuningPlugin;
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Tuning>();
+        app.init_asset_loader::<TuningLoader>();
+        app.init_resource::<Tuning>();
+        app.add_systems(Startup, load_tuning);
+        app.add_systems(
+            Update,
+            (
+                update_tuning_from_asset,
+                (
+                    apply_world_tuning
+                        .run_if(resource_changed::<Tuning>().or_else(resource_changed::<LevelOverrides>())),
+                    tune_riders,
+                    tune_pters,
+                    tune_eggmen,
+                    tune_pter_speed_clamps,
+                    tune_eggman_speed_clamps,
+                ),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// All of the tunable values
+/// This is both the asset that is loaded from the tuning file,
+/// and the resource that holds the values currently in use
+#[derive(Asset, Resource, TypePath, Clone, Serialize, Deserialize)]
+pub struct Tuning {
+    pub gravity: V2,
+    /// All bevy transforms are multiplied by this when converting to Positions
+    pub physics_scale: f32,
+    /// The width of the world, after which entities wrap around
+    pub border_distance: f32,
+    pub rider: RiderSpeedCharacteristics,
+    pub pter: PterSpeedCharacteristics,
+    pub pter_speed_clamps: SpeedClamps,
+    pub eggman: EggmanSpeedCharacteristics,
+    pub eggman_speed_clamps: SpeedClamps,
+    /// Older tuning files do not have this
+    #[serde(default)]
+    pub troll_hand: TrollHandCharacteristics,
+}
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            gravity: V2::new(0.0, -9.81),
+            physics_scale: 15.0,
+            border_distance: 48.0,
+            rider: RiderSpeedCharacteristics::default(),
+            pter: PterSpeedCharacteristics::default(),
+            pter_speed_clamps: default_pter_speed_clamps(),
+            eggman: EggmanSpeedCharacteristics::default(),
+            eggman_speed_clamps: default_eggman_speed_clamps(),
+            troll_hand: TrollHandCharacteristics::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TuningLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TuningLoaderError {
+    #[error("Could not read tuning file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse tuning file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for TuningLoader {
+    type Asset = Tuning;
+    type Settings = ();
+    type Error = TuningLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Tuning, TuningLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<Tuning>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+/// Keeps the tuning file loaded, so that changes to it are picked up
+#[derive(Resource)]
+struct TuningHandle(Handle<Tuning>);
+
+fn load_tuning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(TuningHandle(asset_server.load(TUNING_PATH)));
+}
+
+/// Copies the tuning file into the Tuning resource whenever it is (re)loaded
+fn update_tuning_from_asset(
+    mut events: EventReader<AssetEvent<Tuning>>,
+    handle: Res<TuningHandle>,
+    assets: Res<Assets<Tuning>>,
+    mut tuning: ResMut<Tuning>,
+) {
+    for e in events.read() {
+        match e {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.0.id() =>
+            {
+                if let Some(t) = assets.get(*id) {
+                    *tuning = t.clone();
+                    info!(target: "joust::tuning", "Applied tuning from {}", TUNING_PATH);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The overrides of the current level win over the tuning file
+fn apply_world_tuning(
+    tuning: Res<Tuning>,
+    overrides: Res<LevelOverrides>,
+    mut gravity: ResMut<Gravity>,
+    mut physics_scale: ResMut<PhysicsScale>,
+    mut border_distance: ResMut<BorderDistance>,
+) {
+    gravity.0 = overrides.gravity(&tuning);
+    physics_scale.0 = tuning.physics_scale;
+    border_distance.0 = overrides.border_distance(&tuning);
+}
+
+fn tune_riders(
+    tuning: Res<Tuning>,
+    mut q: Query<&mut RiderSpeedCharacteristics, Without<SpeedOverride>>,
+) {
+    for mut c in q.iter_mut() {
+        if tuning.is_changed() || c.is_added() {
+            *c = tuning.rider;
+        }
+    }
+}
+
+fn tune_pters(tuning: Res<Tuning>, mut q: Query<&mut PterSpeedCharacteristics>) {
+    for mut c in q.iter_mut() {
+        if tuning.is_changed() || c.is_added() {
+            *c = tuning.pter;
+        }
+    }
+}
+
+fn tune_eggmen(tuning: Res<Tuning>, mut q: Query<&mut EggmanSpeedCharacteristics>) {
+    for mut c in q.iter_mut() {
+        if tuning.is_changed() || c.is_added() {
+            *c = tuning.eggman;
+        }
+    }
+}
+
+fn tune_pter_speed_clamps(tuning: Res<Tuning>, mut q: Query<&mut SpeedClamps, With<PterSprite>>) {
+    for mut c in q.iter_mut() {
+        if tuning.is_changed() || c.is_added() {
+            *c = tuning.pter_speed_clamps;
+        }
+    }
+}
+
+fn tune_eggman_speed_clamps(tuning: Res<Tuning>, mut q: Query<&mut SpeedClamps, With<Eggman>>) {
+    for mut c in q.iter_mut() {
+        if tuning.is_changed() || c.is_added() {
+            *c = tuning.eggman_speed_clamps;
+        }
+    }
+}
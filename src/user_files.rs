@@ -0,0 +1,59 @@
+/*
+Files the game keeps for the user, outside of the assets folder
+
+Data files, like the high scores, go in the platform's data directory:
+    Linux: $XDG_DATA_HOME, or ~/.local/share
+    macOS: ~/Library/Application Support
+    Windows: %APPDATA%
+Settings go in the platform's config directory:
+    Linux: $XDG_CONFIG_HOME, or ~/.config
+    macOS and Windows: the same as the data directory
+each in a bevy-joust folder.
+*/
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const APP_FOLDER: &str = "bevy-joust";
+
+/// None when the platform directory can not be found
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env_path("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME").map(|h| h.join("Library").join("Application Support"))
+    } else {
+        env_path("XDG_DATA_HOME").or_else(|| env_path("HOME").map(|h| h.join(".local").join("share")))
+    };
+    base.map(|b| b.join(APP_FOLDER))
+}
+
+/// None when the platform directory can not be found
+pub fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") || cfg!(target_os = "macos") {
+        return data_dir();
+    }
+    env_path("XDG_CONFIG_HOME")
+        .or_else(|| env_path("HOME").map(|h| h.join(".config")))
+        .map(|b| b.join(APP_FOLDER))
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+}
+
+/// Writes a temporary file next to `path` and moves it into place,
+/// so a crash never leaves half a file behind
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
+}